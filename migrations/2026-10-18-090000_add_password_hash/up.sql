@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN password_hash VARCHAR(255) CHECK (password_hash <> '') AFTER name
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE users
+    ADD COLUMN session_id VARCHAR(255) CHECK (session_id <> '') AFTER password_hash;
+
+DROP TABLE sessions
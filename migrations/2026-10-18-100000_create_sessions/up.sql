@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE sessions (
+    id VARCHAR(255) PRIMARY KEY NOT NULL CHECK (id <> ''),
+    user_id BIGINT UNSIGNED NOT NULL,
+    device VARCHAR(255),
+    user_agent VARCHAR(512),
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME NOT NULL,
+    INDEX (user_id),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+-- Keep whoever is logged in right now logged in
+INSERT INTO sessions (id, user_id, expires_at)
+SELECT session_id, id, DATE_ADD(CURRENT_TIMESTAMP, INTERVAL 7 DAY)
+FROM users
+WHERE session_id IS NOT NULL;
+
+ALTER TABLE users DROP COLUMN session_id
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+CREATE INDEX meals_date ON meals (date)
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE rates DROP FOREIGN KEY rates_meal_item;
+ALTER TABLE rates DROP COLUMN meal_item_id;
+
+ALTER TABLE meals ADD COLUMN name VARCHAR(255) NOT NULL DEFAULT '' AFTER id;
+DROP INDEX meals_date ON meals;
+CREATE INDEX meals_date ON meals (date);
+
+INSERT INTO meals (name, date)
+SELECT meal_items.name, meals.date
+FROM meal_items
+JOIN meals ON meals.id = meal_items.meal_id;
+
+DELETE FROM meals WHERE name = '';
+ALTER TABLE meals ALTER COLUMN name DROP DEFAULT;
+
+DROP TABLE meal_items
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+CREATE TABLE meal_items (
+    id SERIAL PRIMARY KEY NOT NULL,
+    meal_id BIGINT UNSIGNED NOT NULL,
+    name VARCHAR(255) NOT NULL CHECK (name <> ''),
+    sort_order INT UNSIGNED NOT NULL DEFAULT 0,
+    UNIQUE (meal_id, name),
+    FOREIGN KEY (meal_id) REFERENCES meals (id) ON DELETE CASCADE
+);
+
+-- meals held one row per dish; every dish becomes an item of the first row of its date
+INSERT IGNORE INTO meal_items (meal_id, name, sort_order)
+SELECT
+    (SELECT MIN(first.id) FROM meals first WHERE first.date = dish.date),
+    dish.name,
+    dish.id
+FROM meals dish;
+
+DELETE dish FROM meals dish
+JOIN meals first ON first.date = dish.date AND first.id < dish.id;
+
+ALTER TABLE meals DROP COLUMN name;
+DROP INDEX meals_date ON meals;
+CREATE UNIQUE INDEX meals_date ON meals (date);
+
+ALTER TABLE rates
+    ADD COLUMN meal_item_id BIGINT UNSIGNED AFTER user_id,
+    ADD CONSTRAINT rates_meal_item FOREIGN KEY (meal_item_id) REFERENCES meal_items (id) ON DELETE SET NULL;
+
+-- Link past ratings to the dish of the same name served that day
+UPDATE rates
+JOIN meals ON meals.date = DATE(rates.created_at)
+JOIN meal_items ON meal_items.meal_id = meals.id AND meal_items.name = rates.food_name
+SET rates.meal_item_id = meal_items.id
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE meal_nutrients;
+
+ALTER TABLE meal_items DROP COLUMN allergens;
+
+ALTER TABLE meals DROP COLUMN calories;
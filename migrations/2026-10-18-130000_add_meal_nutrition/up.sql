@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE meals ADD COLUMN calories FLOAT AFTER date;
+
+-- NEIS allergen codes of the dish, e.g. "5,9,13"
+ALTER TABLE meal_items ADD COLUMN allergens VARCHAR(64) NOT NULL DEFAULT '' AFTER name;
+
+CREATE TABLE meal_nutrients (
+    id SERIAL PRIMARY KEY NOT NULL,
+    meal_id BIGINT UNSIGNED NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    unit VARCHAR(16) NOT NULL,
+    amount FLOAT NOT NULL,
+    UNIQUE (meal_id, name),
+    FOREIGN KEY (meal_id) REFERENCES meals (id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Allergen codes (1~18) the user reacts to, e.g. "1,5"
+ALTER TABLE users
+    ADD COLUMN allergies VARCHAR(64) NOT NULL DEFAULT '' AFTER password_hash
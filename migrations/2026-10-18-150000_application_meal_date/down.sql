@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX applications_user_meal_date ON applications;
+DELETE FROM applications WHERE cancelled_at IS NOT NULL;
+
+ALTER TABLE applications
+    DROP COLUMN cancelled_at,
+    DROP COLUMN meal_date
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE applications
+    ADD COLUMN meal_date DATE AFTER user_id,
+    ADD COLUMN cancelled_at DATETIME AFTER created_at;
+
+UPDATE applications SET meal_date = DATE(created_at);
+
+-- Repeated applies used to insert a row each; keep the first of every day
+DELETE dup FROM applications dup
+JOIN applications first
+    ON first.user_id = dup.user_id AND first.meal_date = dup.meal_date AND first.id < dup.id;
+
+ALTER TABLE applications MODIFY meal_date DATE NOT NULL;
+CREATE UNIQUE INDEX applications_user_meal_date ON applications (user_id, meal_date)
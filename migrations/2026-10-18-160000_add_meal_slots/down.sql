@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM applications WHERE slot <> 2;
+DROP INDEX applications_user_meal ON applications;
+CREATE UNIQUE INDEX applications_user_meal_date ON applications (user_id, meal_date);
+ALTER TABLE applications DROP COLUMN slot;
+
+DELETE FROM meals WHERE slot <> 2;
+DROP INDEX meals_date_slot ON meals;
+CREATE UNIQUE INDEX meals_date ON meals (date);
+ALTER TABLE meals DROP COLUMN slot
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- 1: breakfast, 2: lunch, 3: dinner (NEIS MMEAL_SC_CODE); everything so far was lunch
+ALTER TABLE meals
+    ADD COLUMN slot TINYINT UNSIGNED NOT NULL DEFAULT 2 CHECK (slot BETWEEN 1 AND 3) AFTER date;
+DROP INDEX meals_date ON meals;
+CREATE UNIQUE INDEX meals_date_slot ON meals (date, slot);
+
+ALTER TABLE applications
+    ADD COLUMN slot TINYINT UNSIGNED NOT NULL DEFAULT 2 CHECK (slot BETWEEN 1 AND 3) AFTER meal_date;
+DROP INDEX applications_user_meal_date ON applications;
+CREATE UNIQUE INDEX applications_user_meal ON applications (user_id, meal_date, slot)
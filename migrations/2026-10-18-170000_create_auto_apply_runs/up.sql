@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- One row per meal the auto_apply job handled, so a restart doesn't run it twice
+CREATE TABLE auto_apply_runs (
+    id SERIAL PRIMARY KEY NOT NULL,
+    meal_date DATE NOT NULL,
+    slot TINYINT UNSIGNED NOT NULL CHECK (slot BETWEEN 1 AND 3),
+    applied INT UNSIGNED NOT NULL,
+    ran_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (meal_date, slot)
+)
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX users_grade_class ON users;
+
+ALTER TABLE users
+    DROP COLUMN class,
+    DROP COLUMN grade
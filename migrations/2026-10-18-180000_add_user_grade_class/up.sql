@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Homeroom of a student; NULL for teachers
+ALTER TABLE users
+    ADD COLUMN grade TINYINT UNSIGNED AFTER name,
+    ADD COLUMN class TINYINT UNSIGNED AFTER grade;
+
+CREATE INDEX users_grade_class ON users (grade, class)
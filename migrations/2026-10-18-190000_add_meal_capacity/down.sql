@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX applications_meal ON applications;
+
+ALTER TABLE applications DROP COLUMN waitlisted;
+
+ALTER TABLE meals DROP COLUMN capacity
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- Seats of a meal; NULL falls back to apply.<slot>.capacity of config.yml
+ALTER TABLE meals
+    ADD COLUMN capacity INT UNSIGNED;
+
+-- Applications over capacity wait here, in created_at order, until a seat frees up
+ALTER TABLE applications
+    ADD COLUMN waitlisted BOOLEAN NOT NULL DEFAULT 0;
+
+-- Admission locks this range to count the seats of a meal
+CREATE INDEX applications_meal ON applications (meal_date, slot)
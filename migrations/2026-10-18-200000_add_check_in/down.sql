@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE applications
+    DROP COLUMN checked_in_by,
+    DROP COLUMN checked_in_at
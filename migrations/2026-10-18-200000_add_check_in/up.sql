@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Set when staff scan the student's code at the dining hall
+ALTER TABLE applications
+    ADD COLUMN checked_in_at DATETIME,
+    ADD COLUMN checked_in_by BIGINT UNSIGNED
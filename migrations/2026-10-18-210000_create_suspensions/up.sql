@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- Periods a user can't apply for meals, imposed by the no-show policy or a teacher
+CREATE TABLE suspensions (
+    id SERIAL PRIMARY KEY NOT NULL,
+    user_id BIGINT UNSIGNED NOT NULL,
+    starts_on DATE NOT NULL,
+    -- Last suspended day
+    ends_on DATE NOT NULL,
+    -- No-shows that triggered it; 0 when a teacher imposed it
+    no_shows INT UNSIGNED NOT NULL DEFAULT 0,
+    imposed_by BIGINT UNSIGNED,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    lifted_at DATETIME,
+    lifted_by BIGINT UNSIGNED,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    CHECK (ends_on >= starts_on)
+);
+
+CREATE INDEX suspensions_user ON suspensions (user_id, starts_on)
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE fee_charges;
+DROP TABLE billing_months;
+DROP TABLE fee_exemptions;
+DROP TABLE meal_prices
@@ -0,0 +1,51 @@
+-- Your SQL goes here
+-- Price of a meal slot from `starts_on` until the next price of the slot
+CREATE TABLE meal_prices (
+    id SERIAL PRIMARY KEY NOT NULL,
+    starts_on DATE NOT NULL,
+    slot TINYINT UNSIGNED NOT NULL,
+    -- In won
+    price INT UNSIGNED NOT NULL,
+    created_by BIGINT UNSIGNED,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (starts_on, slot)
+);
+
+-- Students not charged for their meals, e.g. free-meal eligible
+CREATE TABLE fee_exemptions (
+    id SERIAL PRIMARY KEY NOT NULL,
+    user_id BIGINT UNSIGNED NOT NULL,
+    starts_on DATE NOT NULL,
+    -- Last exempt day; NULL while it lasts
+    ends_on DATE,
+    reason VARCHAR(255) NOT NULL DEFAULT '',
+    created_by BIGINT UNSIGNED,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    CHECK (ends_on IS NULL OR ends_on >= starts_on)
+);
+
+CREATE INDEX fee_exemptions_user ON fee_exemptions (user_id, starts_on);
+
+-- Closed months; their charges are frozen in fee_charges
+CREATE TABLE billing_months (
+    -- First day of the month
+    month DATE PRIMARY KEY NOT NULL,
+    closed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_by BIGINT UNSIGNED
+);
+
+-- One row per billed application of a closed month
+CREATE TABLE fee_charges (
+    id SERIAL PRIMARY KEY NOT NULL,
+    month DATE NOT NULL,
+    user_id BIGINT UNSIGNED NOT NULL,
+    meal_date DATE NOT NULL,
+    slot TINYINT UNSIGNED NOT NULL,
+    price INT UNSIGNED NOT NULL,
+    exempt BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY (month) REFERENCES billing_months (month) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX fee_charges_month ON fee_charges (month, user_id)
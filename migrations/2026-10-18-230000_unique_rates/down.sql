@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX total_rates_user_day ON total_rates;
+ALTER TABLE total_rates
+    DROP COLUMN updated_at,
+    DROP COLUMN rated_on;
+
+DROP INDEX rates_user_meal_item ON rates;
+ALTER TABLE rates DROP COLUMN updated_at
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+-- Resubmitting used to insert duplicates; keep the latest rating of each dish
+DELETE older FROM rates older
+JOIN rates newer
+    ON newer.user_id = older.user_id
+    AND newer.meal_item_id = older.meal_item_id
+    AND newer.id > older.id;
+
+ALTER TABLE rates ADD COLUMN updated_at DATETIME;
+CREATE UNIQUE INDEX rates_user_meal_item ON rates (user_id, meal_item_id);
+
+-- The day the total rating is for, one per user
+ALTER TABLE total_rates ADD COLUMN rated_on DATE AFTER rate_level;
+UPDATE total_rates SET rated_on = DATE(created_at);
+
+DELETE older FROM total_rates older
+JOIN total_rates newer
+    ON newer.user_id = older.user_id
+    AND newer.rated_on = older.rated_on
+    AND newer.id > older.id;
+
+ALTER TABLE total_rates
+    MODIFY COLUMN rated_on DATE NOT NULL,
+    ADD COLUMN updated_at DATETIME;
+CREATE UNIQUE INDEX total_rates_user_day ON total_rates (user_id, rated_on)
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE total_rates DROP CHECK total_rates_rate_level;
+ALTER TABLE rates DROP CHECK rates_rate_level
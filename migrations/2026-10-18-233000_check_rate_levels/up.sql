@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Stops with "Check constraint 'run_unhomeal_server_repair_rates_first' is violated" while
+-- ratings with an unknown level remain; `unhomeal_server repair-rates` reports and fixes them
+CREATE TEMPORARY TABLE rate_levels_repaired (
+    repaired BOOL NOT NULL,
+    CONSTRAINT run_unhomeal_server_repair_rates_first CHECK (repaired)
+);
+INSERT INTO rate_levels_repaired (repaired)
+SELECT NOT EXISTS (SELECT 1 FROM rates WHERE rate_level NOT IN (-1, 1, 2))
+    AND NOT EXISTS (SELECT 1 FROM total_rates WHERE rate_level > 10);
+DROP TEMPORARY TABLE rate_levels_repaired;
+
+ALTER TABLE rates ADD CONSTRAINT rates_rate_level CHECK (rate_level IN (-1, 1, 2));
+ALTER TABLE total_rates ADD CONSTRAINT total_rates_rate_level CHECK (rate_level <= 10)
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE survey_answers;
+DROP TABLE survey_questions;
+DROP TABLE survey_templates
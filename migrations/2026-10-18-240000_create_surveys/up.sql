@@ -0,0 +1,53 @@
+-- Your SQL goes here
+-- Versions of a survey are never edited, so past answers keep their questions
+CREATE TABLE survey_templates (
+    id SERIAL PRIMARY KEY NOT NULL,
+    name VARCHAR(255) NOT NULL CHECK (name <> ''),
+    version INT UNSIGNED NOT NULL,
+    -- Meal slot it is asked for; NULL for every meal
+    slot TINYINT UNSIGNED,
+    active_from DATE NOT NULL,
+    created_by BIGINT UNSIGNED,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (name, version)
+);
+
+CREATE INDEX survey_templates_active ON survey_templates (active_from, slot);
+
+CREATE TABLE survey_questions (
+    id SERIAL PRIMARY KEY NOT NULL,
+    template_id BIGINT UNSIGNED NOT NULL,
+    sort_order INT UNSIGNED NOT NULL DEFAULT 0,
+    -- 1 Likert, 2 yes/no, 3 multiple choice, 4 free text
+    kind TINYINT UNSIGNED NOT NULL,
+    prompt VARCHAR(255) NOT NULL CHECK (prompt <> ''),
+    -- Asked about every dish of the meal instead of the meal as a whole
+    per_dish BOOLEAN NOT NULL DEFAULT 0,
+    required BOOLEAN NOT NULL DEFAULT 0,
+    -- Highest point of a Likert question
+    scale TINYINT UNSIGNED,
+    -- JSON array of the options of a multiple choice question
+    choices TEXT,
+    FOREIGN KEY (template_id) REFERENCES survey_templates (id) ON DELETE CASCADE
+);
+
+CREATE TABLE survey_answers (
+    id SERIAL PRIMARY KEY NOT NULL,
+    question_id BIGINT UNSIGNED NOT NULL,
+    user_id BIGINT UNSIGNED NOT NULL,
+    meal_date DATE NOT NULL,
+    slot TINYINT UNSIGNED NOT NULL,
+    -- Dish of a per-dish question; NULL once the dish is withdrawn from the menu
+    meal_item_id BIGINT UNSIGNED,
+    food_name VARCHAR(255),
+    -- Likert point, 1 for yes and 0 for no, or the index of the choice
+    number_value TINYINT UNSIGNED,
+    text_value TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (question_id) REFERENCES survey_questions (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (meal_item_id) REFERENCES meal_items (id) ON DELETE SET NULL
+);
+
+CREATE INDEX survey_answers_meal ON survey_answers (meal_date, slot);
+CREATE INDEX survey_answers_user ON survey_answers (user_id, meal_date)
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE total_rates DROP COLUMN comment;
+ALTER TABLE rates DROP COLUMN comment
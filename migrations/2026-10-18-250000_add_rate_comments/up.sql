@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Why the student rated a dish or the meal as they did, shown to teachers
+ALTER TABLE rates ADD COLUMN comment VARCHAR(1000);
+ALTER TABLE total_rates ADD COLUMN comment VARCHAR(1000)
@@ -0,0 +1,124 @@
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+/// The 18 allergens food labels in Korea must show, by the code NEIS writes after dish names
+pub const ALLERGENS: [(u8, &str); 18] = [
+    (1, "난류"),
+    (2, "우유"),
+    (3, "메밀"),
+    (4, "땅콩"),
+    (5, "대두"),
+    (6, "밀"),
+    (7, "고등어"),
+    (8, "게"),
+    (9, "새우"),
+    (10, "돼지고기"),
+    (11, "복숭아"),
+    (12, "토마토"),
+    (13, "아황산류"),
+    (14, "호두"),
+    (15, "닭고기"),
+    (16, "쇠고기"),
+    (17, "오징어"),
+    (18, "조개류"),
+];
+
+#[derive(Clone, Serialize)]
+pub struct Allergen {
+    pub code: u8,
+    pub name: &'static str,
+}
+
+impl Allergen {
+    pub fn all() -> Vec<Self> {
+        ALLERGENS
+            .iter()
+            .map(|&(code, name)| Self { code, name })
+            .collect()
+    }
+
+    pub fn by_code(code: u8) -> Option<Self> {
+        ALLERGENS
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|&(code, name)| Self { code, name })
+    }
+}
+
+/// Sorts and dedups codes, failing on one outside 1~18
+pub fn normalize(codes: &[u8]) -> Result<Vec<u8>> {
+    if let Some(code) = codes
+        .iter()
+        .find(|code| Allergen::by_code(**code).is_none())
+    {
+        return Err(Error::InvalidParam(format!(
+            "{} is not an allergen code (1~18)",
+            code
+        )));
+    }
+
+    let mut codes = codes.to_vec();
+    codes.sort_unstable();
+    codes.dedup();
+    Ok(codes)
+}
+
+/// Reads a comma separated column like "5,9,13"
+pub fn parse_codes(s: &str) -> Vec<u8> {
+    s.split(',')
+        .filter_map(|code| code.trim().parse().ok())
+        .collect()
+}
+
+pub fn join_codes(codes: &[u8]) -> String {
+    codes
+        .iter()
+        .map(|code| code.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Allergens of a dish that the user reacts to
+pub fn common(dish: &[u8], allergies: &[u8]) -> Vec<u8> {
+    dish.iter()
+        .filter(|code| allergies.contains(code))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_codes() {
+        assert_eq!(normalize(&[13, 5, 9, 5]).unwrap(), vec![5, 9, 13]);
+        assert_eq!(normalize(&[1, 18]).unwrap(), vec![1, 18]);
+        assert!(normalize(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_codes_outside_the_18() {
+        for code in [0, 19, 255] {
+            assert!(matches!(
+                normalize(&[5, code]),
+                Err(Error::InvalidParam(message)) if message.starts_with(&code.to_string())
+            ));
+        }
+    }
+
+    #[test]
+    fn round_trips_the_stored_column() {
+        assert_eq!(join_codes(&[5, 9, 13]), "5,9,13");
+        assert_eq!(parse_codes("5, 9,13"), vec![5, 9, 13]);
+        assert_eq!(parse_codes(""), Vec::<u8>::new());
+        assert_eq!(parse_codes("5,x,9"), vec![5, 9]);
+    }
+
+    #[test]
+    fn warns_about_common_allergens() {
+        assert_eq!(common(&[1, 5, 10], &[5, 10, 16]), vec![5, 10]);
+        assert!(common(&[1], &[]).is_empty());
+    }
+}
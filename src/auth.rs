@@ -0,0 +1,139 @@
+use std::{future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{
+    dev::Payload,
+    error::ErrorInternalServerError,
+    http::header::AUTHORIZATION,
+    web::Data,
+    FromRequest, HttpRequest,
+};
+
+use crate::{
+    db::{
+        models::{
+            session::{SessionConfig, SessionDAO},
+            user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+    token::{TokenKey, UserToken},
+};
+
+/// Cookie set by /login for browser clients
+pub const TOKEN_COOKIE: &str = "token";
+
+/// Reads the token from `Authorization: Bearer <token>`, or the `token` cookie as a fallback
+fn token_from_request(req: &HttpRequest) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned());
+
+    bearer.or_else(|| req.cookie(TOKEN_COOKIE).map(|c| c.value().to_owned()))
+}
+
+struct AuthContext {
+    pool: Option<Data<DbPool>>,
+    key: Option<Data<TokenKey>>,
+    config: Option<Data<SessionConfig>>,
+    token: Option<String>,
+}
+
+impl AuthContext {
+    fn new(req: &HttpRequest) -> Self {
+        Self {
+            pool: req.app_data::<Data<DbPool>>().cloned(),
+            key: req.app_data::<Data<TokenKey>>().cloned(),
+            config: req.app_data::<Data<SessionConfig>>().cloned(),
+            token: token_from_request(req),
+        }
+    }
+
+    /// Verifies the token, then renews its session and loads the owner
+    async fn resolve(self) -> Result<(UserDAO, SessionDAO)> {
+        let (pool, key, config) = match (self.pool, self.key, self.config) {
+            (Some(pool), Some(key), Some(config)) => (pool, key, config),
+            _ => {
+                return Err(ErrorInternalServerError(
+                    "DbPool, TokenKey or SessionConfig is not registered",
+                )
+                .into())
+            }
+        };
+        let token = UserToken::decode(&self.token.ok_or(Error::NoSuchSession)?, &key)?;
+
+        let session = SessionDAO::touch(pool.clone(), &config, token.session).await?;
+        let user = UserDAO::by_id(pool, session.user_id)
+            .await
+            .map_err(Error::no_such_session)?;
+
+        if user.username == token.user {
+            Ok((user, session))
+        } else {
+            Err(Error::NoSuchSession)
+        }
+    }
+}
+
+/// Any logged-in user, with the session the request came through
+/// * Rejects with NoSuchSession when the token is missing, logged out or idle for too long
+pub struct AuthUser {
+    pub user: UserDAO,
+    pub session: SessionDAO,
+}
+
+impl Deref for AuthUser {
+    type Target = UserDAO;
+
+    fn deref(&self) -> &Self::Target {
+        &self.user
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let context = AuthContext::new(req);
+
+        Box::pin(async move {
+            let (user, session) = context.resolve().await?;
+            Ok(AuthUser { user, session })
+        })
+    }
+}
+
+/// Logged-in teacher
+/// * Rejects with Unprivileged when the user is not a teacher
+pub struct TeacherUser(pub UserDAO);
+
+impl Deref for TeacherUser {
+    type Target = UserDAO;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequest for TeacherUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let context = AuthContext::new(req);
+
+        Box::pin(async move {
+            let (user, _) = context.resolve().await?;
+
+            if user.is_teacher {
+                Ok(TeacherUser(user))
+            } else {
+                Err(Error::Unprivileged)
+            }
+        })
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::Duration as StdDuration;
+
+use actix_web::web::Data;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock,
+    db::{
+        models::{
+            application::{ApplicationDAO, ApplyConfig},
+            auto_apply_run::AutoApplyRunDAO,
+            meal::{MealDAO, MealSlot},
+            user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+    penalty::{self, PenaltyConfig},
+};
+
+/// `auto_apply` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoApplyConfig {
+    pub enabled: bool,
+    /// When the job runs every day ("HH:MM")
+    pub time: String,
+    /// The job applies for the meals this many days after it runs
+    pub days_ahead: i64,
+    /// Days without school besides weekends
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for AutoApplyConfig {
+    fn default() -> Self {
+        // Off until a school turns it on; then the evening before, so even breakfast is still
+        // open with the default deadlines
+        Self {
+            enabled: false,
+            time: String::from("18:00"),
+            days_ahead: 1,
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl AutoApplyConfig {
+    pub fn parse_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(self.time.trim(), "%H:%M").ok()
+    }
+
+    pub fn is_school_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&date)
+    }
+
+    /// First run time after `now`
+    fn next_run(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let time = self
+            .parse_time()
+            .expect("auto_apply.time is validated on load");
+        let today = now.date().and_time(time);
+
+        if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        }
+    }
+}
+
+/// Starts the daily auto_apply job in the background
+pub fn spawn(
+    pool: Data<DbPool>,
+    config: AutoApplyConfig,
+    apply_config: ApplyConfig,
+    penalty_config: PenaltyConfig,
+) {
+    if !config.enabled {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        loop {
+            let now = clock::now();
+            let wait = (config.next_run(now) - now)
+                .to_std()
+                .unwrap_or(StdDuration::ZERO);
+            actix_web::rt::time::sleep(wait).await;
+
+            run(pool.clone(), &config, &apply_config, &penalty_config).await;
+        }
+    });
+}
+
+/// Applies auto_apply users for every meal `days_ahead` days from now
+/// * Skips holidays, meals without a menu, meals past their deadline and meals already handled
+/// * Skips users whose applications are suspended
+async fn run(
+    pool: Data<DbPool>,
+    config: &AutoApplyConfig,
+    apply_config: &ApplyConfig,
+    penalty_config: &PenaltyConfig,
+) {
+    let now = clock::now();
+    let date = now.date() + Duration::days(config.days_ahead);
+
+    if !config.is_school_day(date) {
+        log::info!("auto_apply: {} is not a school day, skipped", date);
+        return;
+    }
+
+    let user_ids = match eligible_users(pool.clone(), penalty_config).await {
+        Ok(user_ids) => user_ids,
+        Err(e) => {
+            log::error!("auto_apply: {} failed ({})", date, e);
+            return;
+        }
+    };
+
+    for slot in [MealSlot::Breakfast, MealSlot::Lunch, MealSlot::Dinner] {
+        match run_meal(
+            pool.clone(),
+            apply_config,
+            now,
+            date,
+            slot,
+            user_ids.clone(),
+        )
+        .await
+        {
+            Ok(Some(applied)) => {
+                log::info!("auto_apply: {} {:?}, applied {} users", date, slot, applied)
+            }
+            Ok(None) => (),
+            Err(e) => log::error!("auto_apply: {} {:?} failed ({})", date, slot, e),
+        }
+    }
+}
+
+/// auto_apply users who aren't suspended
+async fn eligible_users(pool: Data<DbPool>, penalty_config: &PenaltyConfig) -> Result<Vec<u64>> {
+    let users = UserDAO::with_auto_apply(pool.clone()).await?;
+    let suspended = penalty::enforce(
+        pool,
+        penalty_config,
+        users.iter().map(|user| user.id).collect(),
+    )
+    .await?;
+
+    let mut user_ids = Vec::new();
+    for user in users {
+        match suspended.get(&user.id) {
+            Some(until) => log::info!("auto_apply: {} is suspended until {}", user.username, until),
+            None => user_ids.push(user.id),
+        }
+    }
+    Ok(user_ids)
+}
+
+/// Returns how many users were applied, or None when the meal is skipped
+async fn run_meal(
+    pool: Data<DbPool>,
+    apply_config: &ApplyConfig,
+    now: NaiveDateTime,
+    date: NaiveDate,
+    slot: MealSlot,
+    user_ids: Vec<u64>,
+) -> Result<Option<u32>> {
+    if now >= apply_config.deadline(date, slot) {
+        return Ok(None);
+    }
+    match MealDAO::find(pool.clone(), date, slot).await {
+        Ok(_) => (),
+        Err(Error::NotFoundOnDB) => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    if AutoApplyRunDAO::exists(pool.clone(), date, slot).await? {
+        return Ok(None);
+    }
+
+    let applied =
+        ApplicationDAO::auto_apply(pool.clone(), apply_config, date, slot, user_ids).await?;
+    AutoApplyRunDAO::record(pool, date, slot, applied, now).await?;
+
+    Ok(Some(applied))
+}
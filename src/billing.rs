@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+
+use actix_web::web::Data;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock,
+    db::{
+        models::{
+            application::{month_range, ApplicationDAO},
+            billing_month::{BillingMonthDAO, NewCharge},
+            fee_charge::FeeChargeDAO,
+            fee_exemption::FeeExemptionDAO,
+            meal::MealSlot,
+            meal_price::MealPriceDAO,
+            user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+/// `billing` section of config.yml
+/// * Prices in won, used for dates before any price set with /billing/prices
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BillingConfig {
+    pub breakfast_price: u32,
+    pub lunch_price: u32,
+    pub dinner_price: u32,
+}
+
+impl BillingConfig {
+    fn default_price(&self, slot: MealSlot) -> u32 {
+        match slot {
+            MealSlot::Breakfast => self.breakfast_price,
+            MealSlot::Lunch => self.lunch_price,
+            MealSlot::Dinner => self.dinner_price,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Charge {
+    pub date: NaiveDate,
+    pub slot: MealSlot,
+    /// In won, before the exemption
+    pub price: u32,
+    pub exempt: bool,
+}
+
+/// What a student owes for a month
+#[derive(Clone, Serialize)]
+pub struct Statement {
+    pub username: String,
+    pub name: String,
+    pub grade: Option<u8>,
+    pub class: Option<u8>,
+    pub meals: u32,
+    pub exempt_meals: u32,
+    /// In won
+    pub amount: u64,
+    pub charges: Vec<Charge>,
+}
+
+impl Statement {
+    fn new(user: UserDAO) -> Self {
+        Self {
+            username: user.username,
+            name: user.name,
+            grade: user.grade,
+            class: user.class,
+            meals: 0,
+            exempt_meals: 0,
+            amount: 0,
+            charges: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, charge: Charge) {
+        self.meals += 1;
+        if charge.exempt {
+            self.exempt_meals += 1;
+        } else {
+            self.amount += charge.price as u64;
+        }
+        self.charges.push(charge);
+    }
+}
+
+/// Statements of every student who had a seat in a month
+pub struct Billing {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// None while the month is open and its charges may still change
+    pub closed_at: Option<NaiveDateTime>,
+    pub statements: Vec<Statement>,
+}
+
+impl Billing {
+    pub fn total(&self) -> u64 {
+        self.statements
+            .iter()
+            .map(|statement| statement.amount)
+            .sum()
+    }
+}
+
+/// Fails with `Error::MonthClosed` when the month of `date` is closed
+pub async fn ensure_open(pool: Data<DbPool>, date: NaiveDate) -> Result<()> {
+    let (month, _) = month_range(&date.format("%Y-%m").to_string())?;
+    match BillingMonthDAO::find(pool, month).await? {
+        Some(_) => Err(Error::MonthClosed(date.format("%Y-%m").to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Billing of a "YYYY-MM" month, frozen once the month is closed
+pub async fn month(pool: Data<DbPool>, config: &BillingConfig, month: &str) -> Result<Billing> {
+    load(pool, config, month, None).await
+}
+
+/// A student's billing of a "YYYY-MM" month
+/// * `statements` holds only theirs, without charges if they had no seat
+pub async fn statement(
+    pool: Data<DbPool>,
+    config: &BillingConfig,
+    month: &str,
+    user: UserDAO,
+) -> Result<Billing> {
+    let mut billing = load(pool, config, month, Some(user.id)).await?;
+    if billing.statements.is_empty() {
+        billing.statements.push(Statement::new(user));
+    }
+    Ok(billing)
+}
+
+/// Billing of the month, of just `user_id` when given
+async fn load(
+    pool: Data<DbPool>,
+    config: &BillingConfig,
+    month: &str,
+    user_id: Option<u64>,
+) -> Result<Billing> {
+    let (from, _) = month_range(month)?;
+
+    match BillingMonthDAO::find(pool.clone(), from).await? {
+        Some(closed) => Ok(Billing {
+            month: closed.month,
+            closed_at: Some(closed.closed_at),
+            statements: frozen(FeeChargeDAO::with_users(pool, closed.month, user_id).await?),
+        }),
+        None => Ok(Billing {
+            month: from,
+            closed_at: None,
+            statements: charge(pool, config, month, user_id).await?.1,
+        }),
+    }
+}
+
+/// Closes a month that has ended, freezing its charges against later changes
+pub async fn close(
+    pool: Data<DbPool>,
+    config: &BillingConfig,
+    month: &str,
+    closed_by: u64,
+) -> Result<Billing> {
+    let (from, to) = month_range(month)?;
+    ensure_ended(month, to, clock::today())?;
+
+    let (user_ids, statements) = charge(pool.clone(), config, month, None).await?;
+    let charges = user_ids
+        .into_iter()
+        .zip(&statements)
+        .flat_map(|(user_id, statement)| {
+            statement.charges.iter().map(move |charge| NewCharge {
+                user_id,
+                meal_date: charge.date,
+                slot: charge.slot,
+                price: charge.price,
+                exempt: charge.exempt,
+            })
+        })
+        .collect();
+    let closed_at = BillingMonthDAO::close(pool, from, closed_by, charges).await?;
+
+    Ok(Billing {
+        month: from,
+        closed_at: Some(closed_at),
+        statements,
+    })
+}
+
+/// Fails with `Error::InvalidParam` until the month ending on `to` is over
+fn ensure_ended(month: &str, to: NaiveDate, today: NaiveDate) -> Result<()> {
+    if to >= today {
+        return Err(Error::InvalidParam(format!(
+            "{} can be closed after it ends",
+            month
+        )));
+    }
+    Ok(())
+}
+
+/// Statements of a closed month, rebuilt from the charges frozen when it closed
+fn frozen(charges: Vec<(FeeChargeDAO, UserDAO)>) -> Vec<Statement> {
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut last_user = None;
+    for (charge, user) in charges {
+        if last_user != Some(user.id) {
+            last_user = Some(user.id);
+            statements.push(Statement::new(user));
+        }
+        if let Some(statement) = statements.last_mut() {
+            statement.push(Charge {
+                date: charge.meal_date,
+                slot: charge.meal_slot(),
+                price: charge.price,
+                exempt: charge.exempt,
+            });
+        }
+    }
+    statements
+}
+
+/// Charges the month's admitted applications, of just `user_id` when given
+/// * Returns the user ids alongside their statements, ordered by class and username
+async fn charge(
+    pool: Data<DbPool>,
+    config: &BillingConfig,
+    month: &str,
+    user_id: Option<u64>,
+) -> Result<(Vec<u64>, Vec<Statement>)> {
+    let (from, to) = month_range(month)?;
+    let prices = MealPriceDAO::up_to(pool.clone(), to).await?;
+    let exemptions = FeeExemptionDAO::overlapping(pool.clone(), from, to).await?;
+    let applications = ApplicationDAO::with_users(pool, from, to, user_id).await?;
+
+    Ok(statements(config, &prices, &exemptions, applications))
+}
+
+/// Price of a meal on `date`: the latest price starting on or before it, else the config's
+/// * `prices` are oldest first, as MealPriceDAO::up_to returns them
+fn price_of(
+    config: &BillingConfig,
+    prices: &[MealPriceDAO],
+    date: NaiveDate,
+    slot: MealSlot,
+) -> u32 {
+    prices
+        .iter()
+        .rev()
+        .find(|price| price.slot == slot.code() && price.starts_on <= date)
+        .map_or_else(|| config.default_price(slot), |price| price.price)
+}
+
+/// Charges every admitted application at the price of its date
+fn statements(
+    config: &BillingConfig,
+    prices: &[MealPriceDAO],
+    exemptions: &[FeeExemptionDAO],
+    applications: Vec<(ApplicationDAO, UserDAO)>,
+) -> (Vec<u64>, Vec<Statement>) {
+    let mut statements = BTreeMap::new();
+    for (application, user) in applications {
+        if application.cancelled_at.is_some() || application.waitlisted {
+            continue;
+        }
+
+        let slot = application.meal_slot();
+        let date = application.meal_date;
+        let exempt = exemptions
+            .iter()
+            .any(|exemption| exemption.user_id == user.id && exemption.covers(date));
+        let key = (user.grade, user.class, user.username.clone(), user.id);
+        statements
+            .entry(key)
+            .or_insert_with(|| Statement::new(user))
+            .push(Charge {
+                date,
+                slot,
+                price: price_of(config, prices, date, slot),
+                exempt,
+            });
+    }
+
+    statements
+        .into_iter()
+        .map(|((_, _, _, user_id), statement)| (user_id, statement))
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BillingConfig {
+        BillingConfig {
+            breakfast_price: 2500,
+            lunch_price: 3000,
+            dinner_price: 3500,
+        }
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2026, 10, day)
+    }
+
+    fn at(day: u32) -> NaiveDateTime {
+        self::day(day).and_hms(9, 0, 0)
+    }
+
+    fn price(starts_on: NaiveDate, slot: MealSlot, price: u32) -> MealPriceDAO {
+        MealPriceDAO {
+            id: 0,
+            starts_on,
+            slot: slot.code(),
+            price,
+            created_by: None,
+            created_at: at(1),
+        }
+    }
+
+    fn user(id: u64, username: &str, class: u8) -> UserDAO {
+        UserDAO {
+            id,
+            username: String::from(username),
+            name: String::from(username),
+            grade: Some(1),
+            class: Some(class),
+            password_hash: None,
+            allergies: String::new(),
+            auto_apply: false,
+            is_teacher: false,
+            created_at: at(1),
+        }
+    }
+
+    fn application(user: &UserDAO, day: u32, slot: MealSlot) -> (ApplicationDAO, UserDAO) {
+        let application = ApplicationDAO {
+            id: 0,
+            user_id: user.id,
+            meal_date: self::day(day),
+            slot: slot.code(),
+            created_at: at(1),
+            cancelled_at: None,
+            waitlisted: false,
+            checked_in_at: None,
+            checked_in_by: None,
+        };
+        (application, user.clone())
+    }
+
+    #[test]
+    fn prices_by_the_latest_price_started() {
+        let prices = [
+            price(day(1), MealSlot::Lunch, 3200),
+            price(day(15), MealSlot::Lunch, 3400),
+            price(day(20), MealSlot::Dinner, 4000),
+        ];
+        let price_of = |date, slot| price_of(&config(), &prices, date, slot);
+
+        assert_eq!(
+            price_of(NaiveDate::from_ymd(2026, 9, 30), MealSlot::Lunch),
+            3000
+        );
+        assert_eq!(price_of(day(1), MealSlot::Lunch), 3200);
+        assert_eq!(price_of(day(14), MealSlot::Lunch), 3200);
+        assert_eq!(price_of(day(15), MealSlot::Lunch), 3400);
+        assert_eq!(price_of(day(31), MealSlot::Lunch), 3400);
+        assert_eq!(price_of(day(19), MealSlot::Dinner), 3500);
+        assert_eq!(price_of(day(20), MealSlot::Dinner), 4000);
+        assert_eq!(price_of(day(20), MealSlot::Breakfast), 2500);
+    }
+
+    #[test]
+    fn charges_admitted_applications_by_class() {
+        let (kim, lee) = (user(1, "kim", 2), user(2, "lee", 1));
+        let mut cancelled = application(&kim, 6, MealSlot::Dinner);
+        cancelled.0.cancelled_at = Some(at(5));
+        let mut waitlisted = application(&kim, 7, MealSlot::Lunch);
+        waitlisted.0.waitlisted = true;
+        let applications = vec![
+            application(&kim, 5, MealSlot::Lunch),
+            application(&lee, 5, MealSlot::Lunch),
+            application(&kim, 6, MealSlot::Breakfast),
+            cancelled,
+            waitlisted,
+        ];
+
+        let (user_ids, statements) = statements(&config(), &[], &[], applications);
+
+        assert_eq!(user_ids, vec![2, 1]);
+        assert_eq!(statements[0].username, "lee");
+        assert_eq!(statements[0].amount, 3000);
+        assert_eq!(statements[1].meals, 2);
+        assert_eq!(statements[1].amount, 5500);
+    }
+
+    #[test]
+    fn leaves_exempt_meals_out_of_the_amount() {
+        let kim = user(1, "kim", 1);
+        let exemption = FeeExemptionDAO {
+            id: 0,
+            user_id: kim.id,
+            starts_on: day(10),
+            ends_on: None,
+            reason: String::from("free meals"),
+            created_by: None,
+            created_at: at(1),
+        };
+        let applications = vec![
+            application(&kim, 9, MealSlot::Lunch),
+            application(&kim, 10, MealSlot::Lunch),
+            application(&kim, 11, MealSlot::Lunch),
+        ];
+
+        let (_, statements) = statements(&config(), &[], &[exemption], applications);
+
+        assert_eq!(statements[0].meals, 3);
+        assert_eq!(statements[0].exempt_meals, 2);
+        assert_eq!(statements[0].amount, 3000);
+        assert!(statements[0].charges[1].exempt);
+        assert_eq!(statements[0].charges[1].price, 3000);
+    }
+
+    #[test]
+    fn keeps_the_prices_a_month_was_closed_with() {
+        let kim = user(1, "kim", 1);
+        let charge = |day: u32, price: u32, exempt: bool| FeeChargeDAO {
+            id: 0,
+            month: self::day(1),
+            user_id: kim.id,
+            meal_date: self::day(day),
+            slot: MealSlot::Lunch.code(),
+            price,
+            exempt,
+        };
+        let frozen = frozen(vec![
+            (charge(5, 3000, false), kim.clone()),
+            (charge(6, 3000, true), kim.clone()),
+            (charge(5, 3000, false), user(2, "lee", 1)),
+        ]);
+
+        assert_eq!(frozen.len(), 2);
+        assert_eq!(frozen[0].amount, 3000);
+        assert_eq!(frozen[0].exempt_meals, 1);
+
+        // The same meals of an open month follow prices set since
+        let prices = [price(day(1), MealSlot::Lunch, 3400)];
+        let applications = vec![
+            application(&kim, 5, MealSlot::Lunch),
+            application(&kim, 6, MealSlot::Lunch),
+        ];
+        let (_, open) = statements(&config(), &prices, &[], applications);
+        assert_eq!(open[0].amount, 6800);
+    }
+
+    #[test]
+    fn closes_a_month_only_after_it_ends() {
+        let (_, to) = month_range("2026-10").unwrap();
+
+        assert!(matches!(
+            ensure_ended("2026-10", to, day(31)),
+            Err(Error::InvalidParam(_))
+        ));
+        assert!(matches!(
+            ensure_ended("2026-10", to, day(15)),
+            Err(Error::InvalidParam(_))
+        ));
+        assert!(ensure_ended("2026-10", to, NaiveDate::from_ymd(2026, 11, 1)).is_ok());
+    }
+}
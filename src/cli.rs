@@ -0,0 +1,245 @@
+use std::{
+    env, fs,
+    io::{self, BufRead},
+};
+
+use actix_web::web::Data;
+use chrono::NaiveDate;
+
+use crate::{
+    billing::BillingConfig,
+    db::{
+        models::{
+            rate::RateDAO,
+            total_rate::{TotalRateDAO, MAX_TOTAL_RATE},
+            user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+    export::{self, Sheet},
+    neis,
+    routes::RateLevel,
+};
+
+pub const USAGE: &str = "\
+usage: unhomeal_server [--config <path>] [command]
+
+commands:
+    serve                 Run the server (default)
+    import-meals <file>   Import menus from a NEIS mealServiceDietInfo export (JSON or XML)
+    export-applications <YYYY-MM> <file> [--bom]
+                          Write the applications of a month to a .csv or .xlsx file
+    export-rates <from> <to> <file> [--bom]
+                          Write the dish ratings of a date range to a .csv or .xlsx file
+    export-billing <YYYY-MM> <file> [--bom]
+                          Write the meal charges of a month per student to a .csv or .xlsx file
+    repair-rates [--dry-run]
+                          Report ratings with an unknown level and set them to the nearest one;
+                          run it before the check_rate_levels migration, which refuses them
+    set-password <username>
+                          Set a user's password to the first line read from standard input
+                          and log out all of the user's sessions
+
+    --bom                 Start CSV files with a UTF-8 BOM so Excel reads Korean names
+    --dry-run             Only report what would change
+
+Accounts start without a password and can't log in until one is set with set-password,
+e.g. `echo 'first-password' | unhomeal_server set-password teacher01`.
+Users change it afterwards through /change_password.";
+
+/// What to run, from the arguments left after `--config <path>`
+pub enum Command {
+    Serve,
+    ImportMeals {
+        path: String,
+    },
+    ExportApplications {
+        month: String,
+        path: String,
+        bom: bool,
+    },
+    ExportRates {
+        from: NaiveDate,
+        to: NaiveDate,
+        path: String,
+        bom: bool,
+    },
+    ExportBilling {
+        month: String,
+        path: String,
+        bom: bool,
+    },
+    RepairRates {
+        dry_run: bool,
+    },
+    SetPassword {
+        username: String,
+    },
+}
+
+impl Command {
+    pub fn from_args() -> Result<Self> {
+        let mut args = Vec::new();
+        let mut rest = env::args().skip(1);
+        while let Some(arg) = rest.next() {
+            if arg == "--config" {
+                rest.next();
+            } else if !arg.starts_with("--config=") {
+                args.push(arg);
+            }
+        }
+
+        let bom = args.iter().any(|arg| arg == "--bom");
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        args.retain(|arg| arg != "--bom" && arg != "--dry-run");
+
+        let usage = || Error::InvalidParam(String::from(USAGE));
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| usage());
+        match args
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [] | ["serve"] => Ok(Self::Serve),
+            ["import-meals", path] => Ok(Self::ImportMeals {
+                path: path.to_string(),
+            }),
+            ["export-applications", month, path] => Ok(Self::ExportApplications {
+                month: month.to_string(),
+                path: path.to_string(),
+                bom,
+            }),
+            ["export-rates", from, to, path] => Ok(Self::ExportRates {
+                from: date(from)?,
+                to: date(to)?,
+                path: path.to_string(),
+                bom,
+            }),
+            ["export-billing", month, path] => Ok(Self::ExportBilling {
+                month: month.to_string(),
+                path: path.to_string(),
+                bom,
+            }),
+            ["repair-rates"] => Ok(Self::RepairRates { dry_run }),
+            ["set-password", username] => Ok(Self::SetPassword {
+                username: username.to_string(),
+            }),
+            _ => Err(usage()),
+        }
+    }
+}
+
+pub async fn import_meals(pool: Data<DbPool>, path: &str) -> Result<()> {
+    let src = fs::read_to_string(path)?;
+    let summary = neis::import(pool, &src).await?;
+
+    println!("Imported {} menus from {}", summary.imported, path);
+    Ok(())
+}
+
+pub async fn export_applications(
+    pool: Data<DbPool>,
+    month: &str,
+    path: &str,
+    bom: bool,
+) -> Result<()> {
+    let sheet = export::applications(pool, month).await?;
+    write_sheet(sheet, path, bom).await
+}
+
+pub async fn export_rates(
+    pool: Data<DbPool>,
+    from: NaiveDate,
+    to: NaiveDate,
+    path: &str,
+    bom: bool,
+) -> Result<()> {
+    let sheet = export::rates(pool, from, to).await?;
+    write_sheet(sheet, path, bom).await
+}
+
+pub async fn export_billing(
+    pool: Data<DbPool>,
+    config: &BillingConfig,
+    month: &str,
+    path: &str,
+    bom: bool,
+) -> Result<()> {
+    let sheet = export::billing(pool, config, month).await?;
+    write_sheet(sheet, path, bom).await
+}
+
+/// Sets ratings with an unknown rate_level to the nearest level and caps total ratings
+pub async fn repair_rates(pool: Data<DbPool>, dry_run: bool) -> Result<()> {
+    let mut levels = Vec::new();
+    for rate in RateDAO::invalid_levels(pool.clone()).await? {
+        let level = RateLevel::nearest(rate.rate_level) as i8;
+        println!(
+            "rate {}: user {}, `{}` on {}, level {} -> {}",
+            rate.id,
+            rate.user_id,
+            rate.food_name,
+            rate.created_at.date(),
+            rate.rate_level,
+            level
+        );
+        levels.push((rate.id, level));
+    }
+    let total_rates = TotalRateDAO::out_of_range(pool.clone()).await?;
+    for total_rate in &total_rates {
+        println!(
+            "total rate {}: user {} on {}, level {} -> {}",
+            total_rate.id,
+            total_rate.user_id,
+            total_rate.rated_on,
+            total_rate.rate_level,
+            MAX_TOTAL_RATE
+        );
+    }
+
+    if dry_run {
+        println!(
+            "Found {} ratings and {} total ratings to repair",
+            levels.len(),
+            total_rates.len()
+        );
+        return Ok(());
+    }
+
+    let repaired = levels.len();
+    RateDAO::set_levels(pool.clone(), levels).await?;
+    let clamped = TotalRateDAO::clamp(pool).await?;
+
+    println!(
+        "Repaired {} ratings and {} total ratings",
+        repaired, clamped
+    );
+    Ok(())
+}
+
+/// Sets the password of a user, read from stdin so it stays out of the shell history
+pub async fn set_password(pool: Data<DbPool>, username: &str) -> Result<()> {
+    let mut user = UserDAO::by_username(pool.clone(), username).await?;
+
+    let mut password = String::new();
+    io::stdin().lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']).to_owned();
+    user.set_password(pool, password).await?;
+
+    println!(
+        "Set the password of {} and logged out its sessions",
+        user.username
+    );
+    Ok(())
+}
+
+/// Writes CSV unless the path ends with .xlsx
+async fn write_sheet(sheet: Sheet, path: &str, bom: bool) -> Result<()> {
+    let rows = sheet.rows.len();
+    sheet.save(path, bom).await?;
+
+    println!("Exported {} rows to {}", rows, path);
+    Ok(())
+}
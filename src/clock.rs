@@ -0,0 +1,23 @@
+use std::sync::OnceLock;
+
+use chrono::{FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, Utc};
+
+static UTC_OFFSET: OnceLock<FixedOffset> = OnceLock::new();
+
+/// Sets the timezone from config.yml; later calls are ignored
+pub fn set_utc_offset(offset: FixedOffset) {
+    let _ = UTC_OFFSET.set(offset);
+}
+
+fn utc_offset() -> FixedOffset {
+    *UTC_OFFSET.get_or_init(|| Local::now().offset().fix())
+}
+
+/// Current wall-clock time in the configured timezone
+pub fn now() -> NaiveDateTime {
+    Utc::now().with_timezone(&utc_offset()).naive_local()
+}
+
+pub fn today() -> NaiveDate {
+    now().date()
+}
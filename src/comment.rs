@@ -0,0 +1,297 @@
+use std::{cmp::Reverse, fs, iter, mem};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::{Error, Result};
+
+/// Size of the comment columns of rates and total_rates
+pub const MAX_COMMENT_CHARS: usize = 1000;
+
+/// `comments` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommentConfig {
+    /// Longest comment in characters
+    pub max_chars: usize,
+    /// Words never allowed in comments, matched ignoring case, punctuation inside a word and
+    /// letters spaced out one by one
+    pub banned_words: Vec<String>,
+    /// File with more banned words, one per line; `#` starts a comment line
+    pub banned_words_path: Option<String>,
+    /// Replace banned words with `*` instead of rejecting the comment
+    pub mask: bool,
+}
+
+impl Default for CommentConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: 300,
+            banned_words: Vec::new(),
+            banned_words_path: None,
+            mask: false,
+        }
+    }
+}
+
+/// Length limit and profanity filter for rating comments
+pub struct CommentFilter {
+    max_chars: usize,
+    /// Normalized, without empty words
+    banned_words: Vec<Vec<char>>,
+    mask: bool,
+}
+
+/// Whether a character is compared; punctuation is skipped so "나.쁜" matches "나쁜"
+fn is_significant(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+/// NFC, so Hangul typed as decomposed jamo (macOS, iOS) matches the same syllables
+fn normalize(word: &str) -> Vec<char> {
+    word.nfc()
+        .filter(|c| is_significant(*c))
+        .map(lowercase)
+        .collect()
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Positions of the compared characters, grouped into the stretches a banned word may span
+/// * Each word is a stretch of its own, so a banned word never spans two neighbouring words
+/// * Letters spaced out one by one, e.g. "나 쁜", are joined into one stretch
+fn segments(chars: &[char]) -> Vec<Vec<usize>> {
+    let mut segments = Vec::new();
+    let mut spaced = Vec::new();
+    let mut word = Vec::new();
+    for (i, c) in chars.iter().copied().chain(iter::once(' ')).enumerate() {
+        if !c.is_whitespace() {
+            if is_significant(c) {
+                word.push(i);
+            }
+            continue;
+        }
+
+        match word.len() {
+            0 => (),
+            1 => spaced.append(&mut word),
+            _ => {
+                if !spaced.is_empty() {
+                    segments.push(mem::take(&mut spaced));
+                }
+                segments.push(mem::take(&mut word));
+            }
+        }
+    }
+    if !spaced.is_empty() {
+        segments.push(spaced);
+    }
+    segments
+}
+
+impl CommentFilter {
+    /// Builds the filter, reading `banned_words_path` if set
+    pub fn new(config: &CommentConfig) -> Result<Self> {
+        let mut words = config.banned_words.clone();
+        if let Some(path) = &config.banned_words_path {
+            words.extend(
+                fs::read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.starts_with('#'))
+                    .map(str::to_owned),
+            );
+        }
+
+        let mut banned_words = words
+            .iter()
+            .map(|word| normalize(word))
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        banned_words.sort();
+        banned_words.dedup();
+        // Longest first, so a word containing another is masked whole
+        banned_words.sort_by_key(|word| Reverse(word.len()));
+
+        Ok(Self {
+            max_chars: config.max_chars,
+            banned_words,
+            mask: config.mask,
+        })
+    }
+
+    /// Trims a comment, NFC normalizes and checks it, None when it's blank
+    /// * Fails with InvalidParam when too long, and with InappropriateComment on a banned word
+    ///   unless masking is on
+    pub fn check(&self, comment: Option<String>) -> Result<Option<String>> {
+        let comment = match comment.map(|comment| comment.trim().nfc().collect::<String>()) {
+            Some(comment) if !comment.is_empty() => comment,
+            _ => return Ok(None),
+        };
+        if comment.chars().count() > self.max_chars {
+            return Err(Error::InvalidParam(format!(
+                "Comments are at most {} characters",
+                self.max_chars
+            )));
+        }
+
+        let mut chars = comment.chars().collect::<Vec<_>>();
+        let mut found = false;
+        for positions in segments(&chars) {
+            let normalized = positions
+                .iter()
+                .map(|&i| lowercase(chars[i]))
+                .collect::<Vec<_>>();
+
+            for word in &self.banned_words {
+                let mut start = 0;
+                while start + word.len() <= normalized.len() {
+                    if normalized[start..start + word.len()] == word[..] {
+                        if !self.mask {
+                            return Err(Error::InappropriateComment);
+                        }
+                        found = true;
+                        for &i in &positions[start..start + word.len()] {
+                            chars[i] = '*';
+                        }
+                        start += word.len();
+                    } else {
+                        start += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(Some(if found {
+            chars.into_iter().collect()
+        } else {
+            comment
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(banned_words: &[&str], mask: bool) -> CommentFilter {
+        CommentFilter::new(&CommentConfig {
+            max_chars: 20,
+            banned_words: banned_words.iter().map(|word| word.to_string()).collect(),
+            banned_words_path: None,
+            mask,
+        })
+        .unwrap()
+    }
+
+    fn check(filter: &CommentFilter, comment: &str) -> Result<Option<String>> {
+        filter.check(Some(comment.to_owned()))
+    }
+
+    #[test]
+    fn trims_and_drops_blank_comments() {
+        let filter = filter(&[], false);
+        assert_eq!(
+            check(&filter, "  맛있어요 \n").unwrap().unwrap(),
+            "맛있어요"
+        );
+        assert!(check(&filter, " \t").unwrap().is_none());
+        assert!(filter.check(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn limits_length_in_characters() {
+        let filter = filter(&[], false);
+        assert!(check(&filter, &"가".repeat(20)).is_ok());
+        assert!(matches!(
+            check(&filter, &"가".repeat(21)),
+            Err(Error::InvalidParam(_))
+        ));
+        // Decomposed, 20 syllables are 40 jamo
+        assert!(check(&filter, &"가".repeat(20).nfd().collect::<String>()).is_ok());
+    }
+
+    #[test]
+    fn rejects_banned_words() {
+        let filter = filter(&["나쁜", "BadWord"], false);
+        for comment in ["정말 나쁜 맛", "badword", "나.쁜", "나 쁜 맛", "정말나쁜맛"]
+        {
+            assert!(
+                matches!(check(&filter, comment), Err(Error::InappropriateComment)),
+                "{}",
+                comment
+            );
+        }
+        assert_eq!(check(&filter, "좋은 맛").unwrap().unwrap(), "좋은 맛");
+    }
+
+    #[test]
+    fn matches_decomposed_hangul() {
+        let nfd = "나쁜".nfd().collect::<String>();
+        assert_ne!(nfd, "나쁜");
+
+        let filter = filter(&["나쁜"], false);
+        assert!(matches!(
+            check(&filter, &format!("정말 {} 맛", nfd)),
+            Err(Error::InappropriateComment)
+        ));
+
+        let filter = self::filter(&[&nfd], true);
+        assert_eq!(
+            check(&filter, "정말 나쁜 맛").unwrap().unwrap(),
+            "정말 ** 맛"
+        );
+    }
+
+    #[test]
+    fn keeps_neighbouring_words_apart() {
+        let filter = filter(&["개새", "시발"], false);
+        for comment in [
+            "한 개 새우",
+            "이 시 발전소",
+            "반찬 개수 새로",
+            "국이 시 발효",
+        ] {
+            assert!(check(&filter, comment).is_ok(), "{}", comment);
+        }
+        assert!(check(&filter, "시 발").is_err());
+    }
+
+    #[test]
+    fn masks_banned_words() {
+        let filter = filter(&["나쁜"], true);
+        assert_eq!(
+            check(&filter, "정말 나쁜 맛").unwrap().unwrap(),
+            "정말 ** 맛"
+        );
+        assert_eq!(check(&filter, "나.쁜").unwrap().unwrap(), "*.*");
+        assert_eq!(check(&filter, "나 쁜 맛").unwrap().unwrap(), "* * 맛");
+        assert_eq!(check(&filter, "나쁜나쁜").unwrap().unwrap(), "****");
+    }
+
+    #[test]
+    fn masks_the_longest_of_overlapping_words() {
+        let filter = filter(&["나쁜", "나쁜놈", "놈"], true);
+        assert_eq!(check(&filter, "나쁜놈아").unwrap().unwrap(), "***아");
+        assert_eq!(check(&filter, "놈나쁜").unwrap().unwrap(), "***");
+    }
+
+    #[test]
+    fn reads_the_word_list_file() {
+        let path = std::env::temp_dir().join(format!("banned-words-{}.txt", std::process::id()));
+        fs::write(&path, "# 급식 게시판 금지어\n나쁜\n\n  못된  \n").unwrap();
+
+        let filter = CommentFilter::new(&CommentConfig {
+            banned_words_path: Some(path.to_string_lossy().into_owned()),
+            ..CommentConfig::default()
+        })
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(check(&filter, "못된 맛").is_err());
+        assert!(check(&filter, "급식 게시판").is_ok());
+        assert_eq!(filter.banned_words.len(), 2);
+    }
+}
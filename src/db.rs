@@ -3,11 +3,10 @@ pub(crate) mod schema;
 
 use crate::db::models::user::UserDAO;
 use actix_web::web::{block, Data};
-
-pub use diesel::prelude::*;
 use diesel::{
     r2d2::{self, ConnectionManager, PooledConnection},
-    MysqlConnection,
+    result::{DatabaseErrorKind, Error::DatabaseError},
+    Connection, MysqlConnection, QueryResult,
 };
 
 pub type DbPool = r2d2::Pool<ConnectionManager<MysqlConnection>>;
@@ -19,17 +18,36 @@ pub async fn get_conn(pool: Data<DbPool>) -> PooledConnection<ConnectionManager<
         .expect("Couldn't get DB connection")
 }
 
-/*pub async fn is_valid_token(pool: Data<DbPool>, user_token: UserToken) -> bool {
-    use schema::users::dsl;
+/// A transaction runs again when MySQL picks it as a deadlock victim, at most this many times
+const DEADLOCK_ATTEMPTS: usize = 3;
+
+/// Runs `f` in a transaction, again if it deadlocks with a concurrent one
+pub fn retry_on_deadlock<T, F>(conn: &mut MysqlConnection, mut f: F) -> QueryResult<T>
+where
+    F: FnMut(&mut MysqlConnection) -> QueryResult<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match conn.transaction(&mut f) {
+            Err(DatabaseError(DatabaseErrorKind::SerializationFailure, _))
+                if attempt < DEADLOCK_ATTEMPTS =>
+            {
+                attempt += 1
+            }
+            result => return result,
+        }
+    }
+}
 
-    let mut conn = get_conn(pool).await;
-    block(move || {
-        dsl::users
-            .filter(dsl::username.eq(user_token.user))
-            .filter(dsl::session_id.eq(Some(user_token.session)))
-            .first::<User>(&mut conn)
-    })
-    .await
-    .unwrap()
-    .is_ok()
-}*/
+/// Connection to the database in `UNHOMEAL_TEST_DATABASE_URL`, in a transaction that is never
+/// committed
+/// * For tests marked `#[ignore]`, run with `cargo test -- --ignored`
+#[cfg(test)]
+pub fn test_connection() -> MysqlConnection {
+    let url =
+        std::env::var("UNHOMEAL_TEST_DATABASE_URL").expect("UNHOMEAL_TEST_DATABASE_URL is not set");
+    let mut conn = MysqlConnection::establish(&url).expect("Couldn't connect to the test DB");
+    conn.begin_test_transaction()
+        .expect("Couldn't start a test transaction");
+    conn
+}
@@ -1,4 +1,17 @@
 pub(crate) mod application;
+pub(crate) mod auto_apply_run;
+pub(crate) mod billing_month;
+pub(crate) mod fee_charge;
+pub(crate) mod fee_exemption;
+pub(crate) mod meal;
+pub(crate) mod meal_item;
+pub(crate) mod meal_nutrient;
+pub(crate) mod meal_price;
 pub(crate) mod rate;
+pub(crate) mod session;
+pub(crate) mod survey_answer;
+pub(crate) mod survey_question;
+pub(crate) mod survey_template;
+pub(crate) mod suspension;
 pub(crate) mod total_rate;
 pub(crate) mod user;
@@ -1,127 +1,912 @@
 use crate::{
+    clock,
     db::{
-        get_conn,
-        schema::applications::{self, dsl},
+        get_conn, retry_on_deadlock,
+        schema::{
+            applications::{self, dsl},
+            meals, users,
+        },
         DbPool,
     },
     error::{Error, Result},
-    routes::{ApplyParam, GetApplicationParam},
+    routes::ApplyParam,
 };
 use actix_web::web::{block, Data};
-use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
-use diesel::prelude::*;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::{
+    dsl::{count, count_distinct, count_star},
+    prelude::*,
+    result::{DatabaseErrorKind, Error::DatabaseError},
+    MysqlConnection,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{meal::MealSlot, user::UserDAO};
+
+/// Applications for a meal close `days_before` days before it, at `time` ("HH:MM")
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlotConfig {
+    pub days_before: i64,
+    pub time: String,
+    /// Seats of every meal in the slot unless the meal sets its own; None is unlimited
+    pub capacity: Option<u32>,
+}
+
+impl SlotConfig {
+    fn new(days_before: i64, time: &str) -> Self {
+        Self {
+            days_before,
+            time: time.to_owned(),
+            capacity: None,
+        }
+    }
+
+    pub fn parse_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(self.time.trim(), "%H:%M").ok()
+    }
+}
 
-use super::user::UserDAO;
+impl Default for SlotConfig {
+    fn default() -> Self {
+        Self::new(0, "09:00")
+    }
+}
+
+/// `apply` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApplyConfig {
+    /// How many days ahead of a meal applications open
+    pub max_days_ahead: i64,
+    pub breakfast: SlotConfig,
+    pub lunch: SlotConfig,
+    pub dinner: SlotConfig,
+}
+
+impl Default for ApplyConfig {
+    fn default() -> Self {
+        Self {
+            max_days_ahead: 31,
+            breakfast: SlotConfig::new(1, "20:00"),
+            lunch: SlotConfig::new(0, "09:00"),
+            dinner: SlotConfig::new(0, "13:00"),
+        }
+    }
+}
+
+impl ApplyConfig {
+    pub fn slot_config(&self, slot: MealSlot) -> &SlotConfig {
+        match slot {
+            MealSlot::Breakfast => &self.breakfast,
+            MealSlot::Lunch => &self.lunch,
+            MealSlot::Dinner => &self.dinner,
+        }
+    }
+
+    /// Last moment an application for the meal can be made or cancelled
+    pub fn deadline(&self, date: NaiveDate, slot: MealSlot) -> NaiveDateTime {
+        let config = self.slot_config(slot);
+        let time = config
+            .parse_time()
+            .expect("deadline time is validated on load");
+
+        (date - Duration::days(config.days_before)).and_time(time)
+    }
+}
 
 #[derive(Clone, Queryable, Insertable)]
 #[diesel(table_name = applications)]
 pub struct ApplicationDAO {
     pub id: u64,
     pub user_id: u64,
+    pub meal_date: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
     pub created_at: NaiveDateTime,
+    /// Set when the user withdrew; applying again clears it
+    pub cancelled_at: Option<NaiveDateTime>,
+    /// Applied over capacity; promoted in created_at order as seats free up
+    pub waitlisted: bool,
+    pub checked_in_at: Option<NaiveDateTime>,
+    /// Staff who scanned the code
+    pub checked_in_by: Option<u64>,
 }
 
-fn next_first_day_of_month(year: i32, month: u32) -> NaiveDate {
+/// State of an application after /apply
+#[derive(Clone, Serialize)]
+pub struct ApplyOutcome {
+    pub applied: bool,
+    /// Applied, but waiting for a seat
+    pub waitlisted: bool,
+    /// False when the application was already in the requested state
+    pub changed: bool,
+}
+
+/// Applications holding or waiting for a seat of a meal
+struct Seats {
+    admitted: u32,
+    waitlisted: u32,
+}
+
+impl Seats {
+    /// Seats a new application can take; newcomers queue behind anyone already waiting
+    fn open(&self, capacity: Option<u32>) -> u32 {
+        match capacity {
+            Some(_) if self.waitlisted > 0 => 0,
+            Some(capacity) => capacity.saturating_sub(self.admitted),
+            None => u32::MAX,
+        }
+    }
+
+    /// Waitlisted applications that fit in the free seats
+    fn promotable(&self, capacity: Option<u32>) -> u32 {
+        match capacity {
+            Some(capacity) => capacity.saturating_sub(self.admitted).min(self.waitlisted),
+            None => self.waitlisted,
+        }
+    }
+}
+
+pub fn next_first_day_of_month(year: i32, month: u32) -> NaiveDate {
     NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap_or(NaiveDate::from_ymd(year + 1, 1, 1))
 }
 
+/// First and last day of a "YYYY-MM" month
+pub fn month_range(month: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let first_day = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| Error::InvalidParam(format!("`{}` is not a month like 2022-09", month)))?;
+    let last_day = next_first_day_of_month(first_day.year(), first_day.month()).pred();
+
+    Ok((first_day, last_day))
+}
+
+/// Which applications a teacher report covers
+#[derive(Clone)]
+pub struct ApplicationFilter {
+    /// Meal dates, both ends included
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub username: Option<String>,
+    pub slot: Option<MealSlot>,
+}
+
 impl ApplicationDAO {
-    pub async fn post(pool: Data<DbPool>, param: ApplyParam) -> Result<()> {
-        let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
-
-        if param.send_date != Local::now().date_naive() {
-            Err(Error::DateChanged)
-        } else {
-            let mut conn = get_conn(pool).await;
-            block(move || {
-                diesel::insert_into(dsl::applications)
-                    .values(dsl::user_id.eq(&user.id))
-                    .execute(&mut conn)
+    /// Applies for (`apply: true`) or cancels a meal until its deadline
+    /// * Repeating a request changes nothing and reports the same state
+    pub async fn post(
+        pool: Data<DbPool>,
+        config: &ApplyConfig,
+        user: &UserDAO,
+        param: ApplyParam,
+    ) -> Result<ApplyOutcome> {
+        let user_id = user.id;
+        let apply = param.apply;
+        let slot = param.slot;
+        let meal_date = param.meal_date()?;
+
+        let now = clock::now();
+        let deadline = config.deadline(meal_date, slot);
+        if now >= deadline {
+            return Err(Error::DeadlinePassed(deadline));
+        }
+        if meal_date > now.date() + Duration::days(config.max_days_ahead) {
+            return Err(Error::InvalidParam(format!(
+                "Applications open {} days ahead",
+                config.max_days_ahead
+            )));
+        }
+
+        let default_capacity = config.slot_config(slot).capacity;
+        let mut conn = get_conn(pool).await;
+        let (changed, waitlisted) = block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                let seats = Self::lock_meal(conn, meal_date, slot)?;
+                let capacity = Self::capacity(conn, meal_date, slot, default_capacity)?;
+                let existing = dsl::applications
+                    .filter(dsl::user_id.eq(user_id))
+                    .filter(dsl::meal_date.eq(meal_date))
+                    .filter(dsl::slot.eq(slot.code()))
+                    .first::<ApplicationDAO>(conn)
+                    .optional()?;
+
+                let waitlisted = seats.open(capacity) == 0;
+
+                match existing {
+                    Some(app) if apply == app.cancelled_at.is_none() => Ok((false, app.waitlisted)),
+                    Some(app) if apply => {
+                        diesel::update(dsl::applications.find(app.id))
+                            .set((
+                                dsl::created_at.eq(now),
+                                dsl::cancelled_at.eq(None::<NaiveDateTime>),
+                                dsl::waitlisted.eq(waitlisted),
+                            ))
+                            .execute(conn)?;
+                        Ok((true, waitlisted))
+                    }
+                    Some(app) => {
+                        diesel::update(dsl::applications.find(app.id))
+                            .set((dsl::cancelled_at.eq(Some(now)), dsl::waitlisted.eq(false)))
+                            .execute(conn)?;
+                        if !app.waitlisted {
+                            Self::promote(conn, meal_date, slot, capacity)?;
+                        }
+                        Ok((true, false))
+                    }
+                    None if apply => {
+                        let inserted = diesel::insert_into(dsl::applications)
+                            .values((
+                                dsl::user_id.eq(user_id),
+                                dsl::meal_date.eq(meal_date),
+                                dsl::slot.eq(slot.code()),
+                                dsl::created_at.eq(now),
+                                dsl::waitlisted.eq(waitlisted),
+                            ))
+                            .execute(conn);
+
+                        match inserted {
+                            Ok(_) => Ok((true, waitlisted)),
+                            // A concurrent request applied first
+                            Err(DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                                Ok((false, waitlisted))
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                    None => Ok((false, false)),
+                }
             })
-            .await??;
-            Ok(())
+        })
+        .await??;
+
+        Ok(ApplyOutcome {
+            applied: apply,
+            waitlisted,
+            changed,
+        })
+    }
+
+    /// Locks the applications of a meal until the transaction ends and counts its seats
+    /// * The lock covers the index range of the meal, so concurrent admissions wait for it
+    fn lock_meal(
+        conn: &mut MysqlConnection,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+    ) -> QueryResult<Seats> {
+        let waitlisted = dsl::applications
+            .filter(dsl::meal_date.eq(meal_date))
+            .filter(dsl::slot.eq(slot.code()))
+            .filter(dsl::cancelled_at.is_null())
+            .select(dsl::waitlisted)
+            .for_update()
+            .load::<bool>(conn)?;
+
+        Ok(Seats {
+            admitted: waitlisted.iter().filter(|w| !**w).count() as u32,
+            waitlisted: waitlisted.iter().filter(|w| **w).count() as u32,
+        })
+    }
+
+    /// Seats of a meal, from its row if set or the slot default otherwise
+    fn capacity(
+        conn: &mut MysqlConnection,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        default_capacity: Option<u32>,
+    ) -> QueryResult<Option<u32>> {
+        let capacity = meals::table
+            .filter(meals::date.eq(meal_date))
+            .filter(meals::slot.eq(slot.code()))
+            .select(meals::capacity)
+            .first::<Option<u32>>(conn)
+            .optional()?
+            .flatten();
+
+        Ok(capacity.or(default_capacity))
+    }
+
+    /// Admits waitlisted applications in created_at order while seats are free
+    /// * The caller must hold the lock of `lock_meal`
+    /// * Returns the users admitted
+    pub fn promote(
+        conn: &mut MysqlConnection,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        capacity: Option<u32>,
+    ) -> QueryResult<Vec<u64>> {
+        let free = Self::lock_meal(conn, meal_date, slot)?.promotable(capacity);
+        if free == 0 {
+            return Ok(Vec::new());
         }
+
+        let promoted = dsl::applications
+            .filter(dsl::meal_date.eq(meal_date))
+            .filter(dsl::slot.eq(slot.code()))
+            .filter(dsl::cancelled_at.is_null())
+            .filter(dsl::waitlisted.eq(true))
+            .order((dsl::created_at.asc(), dsl::id.asc()))
+            .limit(free as i64)
+            .select((dsl::id, dsl::user_id))
+            .load::<(u64, u64)>(conn)?;
+
+        let ids = promoted.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        diesel::update(dsl::applications.filter(dsl::id.eq_any(ids)))
+            .set(dsl::waitlisted.eq(false))
+            .execute(conn)?;
+
+        Ok(promoted.into_iter().map(|(_, user_id)| user_id).collect())
     }
 
-    pub async fn get(
+    /// Changes the seats of a meal (None falls back to the slot default) and fills new seats
+    /// * Returns how many waitlisted applications were admitted
+    pub async fn set_capacity(
         pool: Data<DbPool>,
-        param: GetApplicationParam,
-    ) -> Result<Vec<ApplicationDAO>> {
-        let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
-
-        if user.is_teacher {
-            let mut conn = get_conn(pool).await;
-            block(move || dsl::applications.load::<ApplicationDAO>(&mut conn))
-                .await?
-                .map_err(Into::into)
-        } else {
-            Err(Error::Unprivileged)
-        }
+        config: &ApplyConfig,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        capacity: Option<u32>,
+    ) -> Result<usize> {
+        let default_capacity = config.slot_config(slot).capacity;
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                Self::lock_meal(conn, meal_date, slot)?;
+                let updated = diesel::update(
+                    meals::table
+                        .filter(meals::date.eq(meal_date))
+                        .filter(meals::slot.eq(slot.code())),
+                )
+                .set(meals::capacity.eq(capacity))
+                .execute(conn)?;
+                if updated == 0 {
+                    return Err(diesel::result::Error::NotFound);
+                }
+
+                let capacity = capacity.or(default_capacity);
+                Self::promote(conn, meal_date, slot, capacity).map(|promoted| promoted.len())
+            })
+        })
+        .await?
+        .map_err(Error::not_found_on_db)
     }
 
-    pub async fn get_one_month(
+    /// Place in line of each of the user's waitlisted applications from `from` to `to`, by id
+    /// * One grouped self-join: every application waiting ahead of one, or the one itself
+    pub async fn waitlist_positions(
         pool: Data<DbPool>,
-        param: GetApplicationParam,
-    ) -> Result<Vec<ApplicationDAO>> {
-        let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
-
-        if user.is_teacher {
-            let today = Local::today();
-            let first_day = NaiveDate::from_ymd(today.year(), today.month(), 1);
-            let next_first_day = next_first_day_of_month(today.year(), today.month());
-
-            let mut conn = get_conn(pool).await;
-            block(move || {
-                dsl::applications
-                    .filter(dsl::created_at.ge(first_day.and_hms(0, 0, 0)))
-                    .filter(dsl::created_at.lt(next_first_day.and_hms(0, 0, 0)))
-                    .load::<ApplicationDAO>(&mut conn)
+        user_id: u64,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<HashMap<u64, u32>> {
+        let ahead = diesel::alias!(applications as ahead);
+        let mut conn = get_conn(pool).await;
+        let positions = block(move || {
+            dsl::applications
+                .inner_join(
+                    ahead.on(ahead
+                        .field(dsl::meal_date)
+                        .eq(dsl::meal_date)
+                        .and(ahead.field(dsl::slot).eq(dsl::slot))
+                        .and(ahead.field(dsl::cancelled_at).is_null())
+                        .and(ahead.field(dsl::waitlisted).eq(true))
+                        .and(
+                            ahead.field(dsl::created_at).lt(dsl::created_at).or(ahead
+                                .field(dsl::created_at)
+                                .eq(dsl::created_at)
+                                .and(ahead.field(dsl::id).le(dsl::id))),
+                        )),
+                )
+                .filter(dsl::user_id.eq(user_id))
+                .filter(dsl::meal_date.ge(from))
+                .filter(dsl::meal_date.le(to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(true))
+                .group_by(dsl::id)
+                .select((dsl::id, count(ahead.field(dsl::id))))
+                .load::<(u64, i64)>(&mut conn)
+        })
+        .await??;
+
+        Ok(positions
+            .into_iter()
+            .map(|(id, position)| (id, position as u32))
+            .collect())
+    }
+
+    /// Applies the users for a meal, except those who already applied or cancelled it
+    /// * Users past the capacity are waitlisted in the given order
+    /// * Returns how many applications were created
+    pub async fn auto_apply(
+        pool: Data<DbPool>,
+        config: &ApplyConfig,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        user_ids: Vec<u64>,
+    ) -> Result<u32> {
+        let now = clock::now();
+        let default_capacity = config.slot_config(slot).capacity;
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                let seats = Self::lock_meal(conn, meal_date, slot)?;
+                let capacity = Self::capacity(conn, meal_date, slot, default_capacity)?;
+                let free = seats.open(capacity) as usize;
+
+                let decided = dsl::applications
+                    .filter(dsl::meal_date.eq(meal_date))
+                    .filter(dsl::slot.eq(slot.code()))
+                    .select(dsl::user_id)
+                    .load::<u64>(conn)?;
+
+                let rows = user_ids
+                    .iter()
+                    .filter(|user_id| !decided.contains(user_id))
+                    .enumerate()
+                    .map(|(i, user_id)| {
+                        (
+                            dsl::user_id.eq(*user_id),
+                            dsl::meal_date.eq(meal_date),
+                            dsl::slot.eq(slot.code()),
+                            dsl::created_at.eq(now),
+                            dsl::waitlisted.eq(i >= free),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if rows.is_empty() {
+                    return Ok(0);
+                }
+
+                diesel::insert_into(dsl::applications)
+                    .values(&rows)
+                    .execute(conn)
+                    .map(|inserted| inserted as u32)
             })
-            .await?
-            .map_err(Into::into)
-        } else {
-            Err(Error::Unprivileged)
-        }
+        })
+        .await?
+        .map_err(Into::into)
     }
 
-    pub async fn get_one<T>(pool: Data<DbPool>, session_id: T) -> Result<ApplicationDAO>
-    where
-        T: Into<String>,
-    {
-        let user = UserDAO::by_session_id(pool.clone(), session_id.into()).await?;
+    /// Admitted applications with their users, ordered by grade, class, username and meal
+    pub async fn roster(
+        pool: Data<DbPool>,
+        filter: ApplicationFilter,
+    ) -> Result<Vec<(ApplicationDAO, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(filter.from))
+                .filter(dsl::meal_date.le(filter.to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .order((
+                    users::grade.asc(),
+                    users::class.asc(),
+                    users::username.asc(),
+                    dsl::meal_date.asc(),
+                    dsl::slot.asc(),
+                ))
+                .into_boxed();
+            if let Some(username) = filter.username {
+                query = query.filter(users::username.eq(username));
+            }
+            if let Some(slot) = filter.slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(ApplicationDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Records that the user showed up for an admitted application of the meal
+    /// * Returns when the user checked in
+    pub async fn check_in(
+        pool: Data<DbPool>,
+        user: &UserDAO,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        staff_id: u64,
+    ) -> Result<NaiveDateTime> {
+        enum Outcome {
+            CheckedIn(NaiveDateTime),
+            NotApplied,
+            AlreadyCheckedIn(NaiveDateTime),
+        }
+
+        let user_id = user.id;
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        let outcome = block(move || {
+            conn.transaction(|conn| {
+                let application = dsl::applications
+                    .filter(dsl::user_id.eq(user_id))
+                    .filter(dsl::meal_date.eq(meal_date))
+                    .filter(dsl::slot.eq(slot.code()))
+                    .for_update()
+                    .first::<ApplicationDAO>(conn)
+                    .optional()?;
 
-        if user.is_teacher {
-            let mut conn = get_conn(pool).await;
-            block(move || {
-                dsl::applications
-                    .filter(dsl::user_id.eq(&user.id))
-                    .first(&mut conn)
+                match application {
+                    Some(app) if app.cancelled_at.is_some() || app.waitlisted => {
+                        Ok(Outcome::NotApplied)
+                    }
+                    Some(app) => match app.checked_in_at {
+                        Some(at) => Ok(Outcome::AlreadyCheckedIn(at)),
+                        None => {
+                            diesel::update(dsl::applications.find(app.id))
+                                .set((
+                                    dsl::checked_in_at.eq(Some(now)),
+                                    dsl::checked_in_by.eq(Some(staff_id)),
+                                ))
+                                .execute(conn)?;
+                            Ok::<_, diesel::result::Error>(Outcome::CheckedIn(now))
+                        }
+                    },
+                    None => Ok(Outcome::NotApplied),
+                }
             })
-            .await?
-            .map_err(Error::not_found_on_db)
-        } else {
-            Err(Error::Unprivileged)
+        })
+        .await??;
+
+        match outcome {
+            Outcome::CheckedIn(at) => Ok(at),
+            Outcome::NotApplied => Err(Error::NotApplied(user.username.clone())),
+            Outcome::AlreadyCheckedIn(at) => Err(Error::AlreadyCheckedIn(at)),
         }
     }
+
+    /// Admitted applications and check-ins per user, for meals of the filter
+    pub async fn attendance(
+        pool: Data<DbPool>,
+        filter: ApplicationFilter,
+    ) -> Result<Vec<(UserDAO, i64, i64)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(filter.from))
+                .filter(dsl::meal_date.le(filter.to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .group_by(users::id)
+                .select((users::all_columns, count_star(), count(dsl::checked_in_at)))
+                .order((
+                    users::grade.asc(),
+                    users::class.asc(),
+                    users::username.asc(),
+                ))
+                .into_boxed();
+            if let Some(username) = filter.username {
+                query = query.filter(users::username.eq(username));
+            }
+            if let Some(slot) = filter.slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(UserDAO, i64, i64)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Meals from `from` through `to` each of the users was admitted to and never checked in for
+    pub async fn no_shows(
+        pool: Data<DbPool>,
+        user_ids: Vec<u64>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(u64, NaiveDate)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::applications
+                .filter(dsl::user_id.eq_any(user_ids))
+                .filter(dsl::meal_date.ge(from))
+                .filter(dsl::meal_date.le(to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .filter(dsl::checked_in_at.is_null())
+                .select((dsl::user_id, dsl::meal_date))
+                .load::<(u64, NaiveDate)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Applications with their users, cancelled ones included, ordered by meal and username
+    /// * Only `user_id`'s when given
+    pub async fn with_users(
+        pool: Data<DbPool>,
+        from: NaiveDate,
+        to: NaiveDate,
+        user_id: Option<u64>,
+    ) -> Result<Vec<(ApplicationDAO, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(from))
+                .filter(dsl::meal_date.le(to))
+                .order((
+                    dsl::meal_date.asc(),
+                    dsl::slot.asc(),
+                    users::grade.asc(),
+                    users::class.asc(),
+                    users::username.asc(),
+                ))
+                .into_boxed();
+            if let Some(user_id) = user_id {
+                query = query.filter(dsl::user_id.eq(user_id));
+            }
+            query.load::<(ApplicationDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Admitted applications per meal
+    pub async fn headcount_by_meal(
+        pool: Data<DbPool>,
+        filter: ApplicationFilter,
+    ) -> Result<Vec<(NaiveDate, u8, i64)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(filter.from))
+                .filter(dsl::meal_date.le(filter.to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .group_by((dsl::meal_date, dsl::slot))
+                .select((dsl::meal_date, dsl::slot, count_star()))
+                .order((dsl::meal_date.asc(), dsl::slot.asc()))
+                .into_boxed();
+            if let Some(username) = filter.username {
+                query = query.filter(users::username.eq(username));
+            }
+            if let Some(slot) = filter.slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(NaiveDate, u8, i64)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Admitted applications and distinct applicants per slot over the whole range
+    pub async fn headcount_by_slot(
+        pool: Data<DbPool>,
+        filter: ApplicationFilter,
+    ) -> Result<Vec<(u8, i64, i64)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(filter.from))
+                .filter(dsl::meal_date.le(filter.to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .group_by(dsl::slot)
+                .select((dsl::slot, count_star(), count_distinct(dsl::user_id)))
+                .order(dsl::slot.asc())
+                .into_boxed();
+            if let Some(username) = filter.username {
+                query = query.filter(users::username.eq(username));
+            }
+            if let Some(slot) = filter.slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(u8, i64, i64)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Admitted applications and distinct applicants per grade, class and slot
+    pub async fn headcount_by_class(
+        pool: Data<DbPool>,
+        filter: ApplicationFilter,
+    ) -> Result<Vec<(Option<u8>, Option<u8>, u8, i64, i64)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::applications
+                .inner_join(users::table)
+                .filter(dsl::meal_date.ge(filter.from))
+                .filter(dsl::meal_date.le(filter.to))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .group_by((users::grade, users::class, dsl::slot))
+                .select((
+                    users::grade,
+                    users::class,
+                    dsl::slot,
+                    count_star(),
+                    count_distinct(dsl::user_id),
+                ))
+                .order((users::grade.asc(), users::class.asc(), dsl::slot.asc()))
+                .into_boxed();
+            if let Some(username) = filter.username {
+                query = query.filter(users::username.eq(username));
+            }
+            if let Some(slot) = filter.slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(Option<u8>, Option<u8>, u8, i64, i64)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Users admitted to a meal, waitlisted ones excluded
+    pub async fn applicants(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+        slot: MealSlot,
+    ) -> Result<Vec<UserDAO>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::applications
+                .inner_join(users::table.on(users::id.eq(dsl::user_id)))
+                .filter(dsl::meal_date.eq(date))
+                .filter(dsl::slot.eq(slot.code()))
+                .filter(dsl::cancelled_at.is_null())
+                .filter(dsl::waitlisted.eq(false))
+                .select(users::all_columns)
+                .order(users::username.asc())
+                .load::<UserDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Applications of a user, cancelled ones included, for meals from `from` to `to`
+    pub async fn by_user(
+        pool: Data<DbPool>,
+        user_id: u64,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ApplicationDAO>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::applications
+                .filter(dsl::user_id.eq(user_id))
+                .filter(dsl::meal_date.ge(from))
+                .filter(dsl::meal_date.le(to))
+                .order((dsl::meal_date.asc(), dsl::slot.asc()))
+                .load::<ApplicationDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    pub fn meal_slot(&self) -> MealSlot {
+        MealSlot::from_code(self.slot).unwrap_or_default()
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Application {
     pub username: String,
+    pub name: String,
+    pub grade: Option<u8>,
+    pub class: Option<u8>,
+    pub date: NaiveDate,
+    pub slot: MealSlot,
+    /// When the application was made
     pub created_at: NaiveDateTime,
 }
 
-impl Application {
-    pub async fn from_application_dto(
-        pool: Data<DbPool>,
-        original: ApplicationDAO,
-    ) -> Result<Self> {
-        let user = UserDAO::by_id(pool, original.user_id).await?;
+impl From<(ApplicationDAO, UserDAO)> for Application {
+    fn from((application, user): (ApplicationDAO, UserDAO)) -> Self {
+        Self {
+            username: user.username,
+            name: user.name,
+            grade: user.grade,
+            class: user.class,
+            date: application.meal_date,
+            slot: application.meal_slot(),
+            created_at: application.created_at,
+        }
+    }
+}
 
-        Ok(Self {
-            username: user.username.clone(),
-            created_at: user.created_at,
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2026, 10, day)
+    }
+
+    #[test]
+    fn closes_each_slot_at_its_deadline() {
+        let config = ApplyConfig::default();
+
+        assert_eq!(
+            config.deadline(day(20), MealSlot::Breakfast),
+            day(19).and_hms(20, 0, 0)
+        );
+        assert_eq!(
+            config.deadline(day(20), MealSlot::Lunch),
+            day(20).and_hms(9, 0, 0)
+        );
+        assert_eq!(
+            config.deadline(day(20), MealSlot::Dinner),
+            day(20).and_hms(13, 0, 0)
+        );
+    }
+
+    #[test]
+    fn counts_days_before_across_months() {
+        let config = ApplyConfig {
+            lunch: SlotConfig::new(3, " 17:30 "),
+            ..ApplyConfig::default()
+        };
+
+        assert_eq!(
+            config.deadline(NaiveDate::from_ymd(2026, 11, 2), MealSlot::Lunch),
+            day(30).and_hms(17, 30, 0)
+        );
+    }
+
+    #[test]
+    fn reads_deadline_times() {
+        assert!(SlotConfig::new(0, "09:00").parse_time().is_some());
+        for time in ["9", "25:00", "09:60", "noon", ""] {
+            assert!(SlotConfig::new(0, time).parse_time().is_none(), "{}", time);
+        }
+    }
+
+    #[test]
+    fn spans_a_whole_month() {
+        assert_eq!(month_range("2026-10").unwrap(), (day(1), day(31)));
+        assert_eq!(
+            month_range(" 2026-12 ").unwrap(),
+            (
+                NaiveDate::from_ymd(2026, 12, 1),
+                NaiveDate::from_ymd(2026, 12, 31)
+            )
+        );
+        assert_eq!(
+            month_range("2028-02").unwrap().1,
+            NaiveDate::from_ymd(2028, 2, 29)
+        );
+
+        for month in ["2026-13", "2026", "October"] {
+            assert!(matches!(month_range(month), Err(Error::InvalidParam(_))));
+        }
+    }
+
+    fn seats(admitted: u32, waitlisted: u32) -> Seats {
+        Seats {
+            admitted,
+            waitlisted,
+        }
+    }
+
+    #[test]
+    fn admits_newcomers_while_seats_are_free() {
+        assert_eq!(seats(0, 0).open(Some(3)), 3);
+        assert_eq!(seats(2, 0).open(Some(3)), 1);
+        assert_eq!(seats(3, 0).open(Some(3)), 0);
+        assert_eq!(seats(1000, 0).open(None), u32::MAX);
+    }
+
+    #[test]
+    fn queues_newcomers_behind_the_waitlist() {
+        // A free seat goes to whoever is already waiting
+        assert_eq!(seats(2, 1).open(Some(5)), 0);
+        // Over capacity after it was lowered
+        assert_eq!(seats(5, 0).open(Some(3)), 0);
+    }
+
+    #[test]
+    fn promotes_as_many_as_fit() {
+        assert_eq!(seats(3, 4).promotable(Some(3)), 0);
+        assert_eq!(seats(2, 4).promotable(Some(3)), 1);
+        assert_eq!(seats(0, 2).promotable(Some(3)), 2);
+        assert_eq!(seats(5, 2).promotable(Some(3)), 0);
+        assert_eq!(seats(5, 2).promotable(None), 2);
+        assert_eq!(seats(5, 0).promotable(None), 0);
     }
 }
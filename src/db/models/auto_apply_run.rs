@@ -0,0 +1,66 @@
+use crate::{
+    db::{
+        get_conn,
+        schema::auto_apply_runs::{self, dsl},
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use super::meal::MealSlot;
+
+/// What the auto_apply job did for a meal
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = auto_apply_runs)]
+pub struct AutoApplyRunDAO {
+    pub id: u64,
+    pub meal_date: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
+    /// Number of applications it created
+    pub applied: u32,
+    pub ran_at: NaiveDateTime,
+}
+
+impl AutoApplyRunDAO {
+    pub async fn exists(pool: Data<DbPool>, meal_date: NaiveDate, slot: MealSlot) -> Result<bool> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::auto_apply_runs
+                .filter(dsl::meal_date.eq(meal_date))
+                .filter(dsl::slot.eq(slot.code()))
+                .select(dsl::id)
+                .first::<u64>(&mut conn)
+                .optional()
+        })
+        .await?
+        .map(|id| id.is_some())
+        .map_err(Into::into)
+    }
+
+    pub async fn record(
+        pool: Data<DbPool>,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        applied: u32,
+        ran_at: NaiveDateTime,
+    ) -> Result<()> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            diesel::insert_into(dsl::auto_apply_runs)
+                .values((
+                    dsl::meal_date.eq(meal_date),
+                    dsl::slot.eq(slot.code()),
+                    dsl::applied.eq(applied),
+                    dsl::ran_at.eq(ran_at),
+                ))
+                .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    clock,
+    db::{
+        get_conn,
+        schema::{
+            billing_months::{self, dsl},
+            fee_charges,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error::DatabaseError},
+};
+
+use super::meal::MealSlot;
+
+/// A closed billing month; its charges are FeeChargeDAO rows
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = billing_months)]
+pub struct BillingMonthDAO {
+    /// First day of the month
+    pub month: NaiveDate,
+    pub closed_at: NaiveDateTime,
+    pub closed_by: Option<u64>,
+}
+
+/// A charge to freeze when closing a month
+pub struct NewCharge {
+    pub user_id: u64,
+    pub meal_date: NaiveDate,
+    pub slot: MealSlot,
+    pub price: u32,
+    pub exempt: bool,
+}
+
+impl BillingMonthDAO {
+    /// The closed month starting on `month`, or None while it's open
+    pub async fn find(pool: Data<DbPool>, month: NaiveDate) -> Result<Option<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::billing_months
+                .find(month)
+                .first::<BillingMonthDAO>(&mut conn)
+                .optional()
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Closes the month and freezes its charges in one transaction
+    /// * Fails with `Error::MonthClosed` when someone closed it first
+    pub async fn close(
+        pool: Data<DbPool>,
+        month: NaiveDate,
+        closed_by: u64,
+        charges: Vec<NewCharge>,
+    ) -> Result<NaiveDateTime> {
+        let closed_at = clock::now();
+        let mut conn = get_conn(pool).await;
+        let inserted = block(move || {
+            conn.transaction(|conn| {
+                diesel::insert_into(dsl::billing_months)
+                    .values((
+                        dsl::month.eq(month),
+                        dsl::closed_at.eq(closed_at),
+                        dsl::closed_by.eq(Some(closed_by)),
+                    ))
+                    .execute(conn)?;
+
+                let rows = charges
+                    .iter()
+                    .map(|charge| {
+                        (
+                            fee_charges::month.eq(month),
+                            fee_charges::user_id.eq(charge.user_id),
+                            fee_charges::meal_date.eq(charge.meal_date),
+                            fee_charges::slot.eq(charge.slot.code()),
+                            fee_charges::price.eq(charge.price),
+                            fee_charges::exempt.eq(charge.exempt),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if !rows.is_empty() {
+                    diesel::insert_into(fee_charges::table)
+                        .values(&rows)
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+
+        match inserted {
+            Ok(()) => Ok(closed_at),
+            Err(DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                Err(Error::MonthClosed(month.format("%Y-%m").to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
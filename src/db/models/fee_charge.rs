@@ -0,0 +1,67 @@
+use crate::{
+    db::{
+        get_conn,
+        schema::{
+            fee_charges::{self, dsl},
+            users,
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+use super::{meal::MealSlot, user::UserDAO};
+
+/// A billed application of a closed month, frozen when the month was closed
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = fee_charges)]
+pub struct FeeChargeDAO {
+    pub id: u64,
+    /// First day of the month
+    pub month: NaiveDate,
+    pub user_id: u64,
+    pub meal_date: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
+    /// In won, before the exemption
+    pub price: u32,
+    pub exempt: bool,
+}
+
+impl FeeChargeDAO {
+    /// Charges of a closed month with their students, ordered by class, student and meal
+    /// * Only `user_id`'s when given
+    pub async fn with_users(
+        pool: Data<DbPool>,
+        month: NaiveDate,
+        user_id: Option<u64>,
+    ) -> Result<Vec<(Self, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::fee_charges
+                .inner_join(users::table)
+                .filter(dsl::month.eq(month))
+                .order((
+                    users::grade.asc(),
+                    users::class.asc(),
+                    users::username.asc(),
+                    dsl::meal_date.asc(),
+                    dsl::slot.asc(),
+                ))
+                .into_boxed();
+            if let Some(user_id) = user_id {
+                query = query.filter(dsl::user_id.eq(user_id));
+            }
+            query.load::<(FeeChargeDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    pub fn meal_slot(&self) -> MealSlot {
+        MealSlot::from_code(self.slot).unwrap_or_default()
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    clock,
+    db::{
+        get_conn,
+        schema::{
+            fee_exemptions::{self, dsl},
+            users,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use super::user::UserDAO;
+
+/// A period a student isn't charged for meals, e.g. while free-meal eligible
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = fee_exemptions)]
+pub struct FeeExemptionDAO {
+    pub id: u64,
+    pub user_id: u64,
+    pub starts_on: NaiveDate,
+    /// Last exempt day; None while it lasts
+    pub ends_on: Option<NaiveDate>,
+    pub reason: String,
+    pub created_by: Option<u64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl FeeExemptionDAO {
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        self.starts_on <= date && self.ends_on.is_none_or(|ends_on| date <= ends_on)
+    }
+
+    pub async fn find(pool: Data<DbPool>, id: u64) -> Result<Self> {
+        let mut conn = get_conn(pool).await;
+        block(move || dsl::fee_exemptions.find(id).first::<Self>(&mut conn))
+            .await?
+            .map_err(Error::not_found_on_db)
+    }
+
+    /// Exemptions covering any day from `from` to `to`
+    pub async fn overlapping(
+        pool: Data<DbPool>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::fee_exemptions
+                .filter(dsl::starts_on.le(to))
+                .filter(dsl::ends_on.is_null().or(dsl::ends_on.ge(from)))
+                .load::<FeeExemptionDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Every exemption with its student, latest first
+    pub async fn with_users(pool: Data<DbPool>) -> Result<Vec<(Self, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::fee_exemptions
+                .inner_join(users::table)
+                .order((dsl::starts_on.desc(), users::username.asc()))
+                .load::<(FeeExemptionDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    pub async fn create(
+        pool: Data<DbPool>,
+        user_id: u64,
+        starts_on: NaiveDate,
+        ends_on: Option<NaiveDate>,
+        reason: String,
+        created_by: u64,
+    ) -> Result<()> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            diesel::insert_into(dsl::fee_exemptions)
+                .values((
+                    dsl::user_id.eq(user_id),
+                    dsl::starts_on.eq(starts_on),
+                    dsl::ends_on.eq(ends_on),
+                    dsl::reason.eq(reason),
+                    dsl::created_by.eq(Some(created_by)),
+                    dsl::created_at.eq(now),
+                ))
+                .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Sets the last exempt day of an exemption
+    pub async fn end(pool: Data<DbPool>, id: u64, ends_on: NaiveDate) -> Result<()> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            diesel::update(dsl::fee_exemptions.find(id))
+                .set(dsl::ends_on.eq(Some(ends_on)))
+                .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exemption(starts_on: NaiveDate, ends_on: Option<NaiveDate>) -> FeeExemptionDAO {
+        FeeExemptionDAO {
+            id: 0,
+            user_id: 1,
+            starts_on,
+            ends_on,
+            reason: String::from("free meals"),
+            created_by: None,
+            created_at: starts_on.and_hms(0, 0, 0),
+        }
+    }
+
+    #[test]
+    fn covers_its_first_and_last_day() {
+        let day = |day: u32| NaiveDate::from_ymd(2026, 10, day);
+        let exemption = exemption(day(10), Some(day(20)));
+
+        assert!(!exemption.covers(day(9)));
+        assert!(exemption.covers(day(10)));
+        assert!(exemption.covers(day(20)));
+        assert!(!exemption.covers(day(21)));
+    }
+
+    #[test]
+    fn covers_every_day_on_while_it_lasts() {
+        let exemption = exemption(NaiveDate::from_ymd(2026, 10, 10), None);
+
+        assert!(!exemption.covers(NaiveDate::from_ymd(2026, 10, 9)));
+        assert!(exemption.covers(NaiveDate::from_ymd(2030, 3, 1)));
+    }
+}
@@ -1,11 +1,343 @@
-use crate::db::schema::meals::{self, dsl::*};
-use chrono::NaiveDateTime;
-use diesel::prelude::*;
+use crate::{
+    allergen,
+    db::{
+        get_conn,
+        schema::{
+            meal_items, meal_nutrients,
+            meals::{self, dsl},
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+    neis::{NeisMeal, NeisNutrient},
+};
+use actix_web::web::{block, Data};
+use chrono::NaiveDate;
+use diesel::{prelude::*, MysqlConnection};
+use serde::{Deserialize, Serialize};
 
+/// Meal of the day, stored by its NEIS MMEAL_SC_CODE
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MealSlot {
+    Breakfast = 1,
+    #[default]
+    Lunch = 2,
+    Dinner = 3,
+}
+
+impl MealSlot {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Breakfast),
+            2 => Some(Self::Lunch),
+            3 => Some(Self::Dinner),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Menu of a meal; its dishes are MealItemDAO rows
 #[derive(Clone, Queryable, Insertable)]
 #[diesel(table_name = meals)]
 pub struct MealDAO {
     pub id: u64,
-    pub name: String,
-    pub created_at: NaiveDate,
+    pub date: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
+    pub calories: Option<f32>,
+    /// Seats, overriding the capacity of the slot in config.yml
+    pub capacity: Option<u32>,
+}
+
+impl MealDAO {
+    pub async fn find(pool: Data<DbPool>, date: NaiveDate, slot: MealSlot) -> Result<Self> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meals
+                .filter(dsl::date.eq(date))
+                .filter(dsl::slot.eq(slot.code()))
+                .first::<MealDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Error::not_found_on_db)
+    }
+
+    pub fn meal_slot(&self) -> MealSlot {
+        MealSlot::from_code(self.slot).unwrap_or_default()
+    }
+
+    /// Meals served from `from` to `to` (both inclusive), by date and slot
+    pub async fn by_range(pool: Data<DbPool>, from: NaiveDate, to: NaiveDate) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meals
+                .filter(dsl::date.ge(from))
+                .filter(dsl::date.le(to))
+                .order((dsl::date.asc(), dsl::slot.asc()))
+                .load::<MealDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Publishes the menu of a meal, in the given dish order, with each dish's allergen codes
+    /// * Dishes that stay on the menu keep their id, so ratings linked to them survive
+    /// * A dish without codes keeps the ones it had on the menu
+    pub async fn publish(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+        slot: MealSlot,
+        dishes: Vec<(String, Option<Vec<u8>>)>,
+    ) -> Result<()> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            conn.transaction(|conn| {
+                let meal_id = Self::find_or_insert(conn, date, slot)?;
+                let names = dishes
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect::<Vec<_>>();
+                Self::replace_items(conn, meal_id, &names)?;
+
+                for (name, allergens) in &dishes {
+                    if let Some(allergens) = allergens {
+                        Self::set_allergens(conn, meal_id, name, allergens)?;
+                    }
+                }
+                Ok::<_, diesel::result::Error>(())
+            })
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Upserts meals read from a NEIS export, all or nothing
+    /// * Importing the same export again leaves the tables as they are
+    pub async fn import(pool: Data<DbPool>, meals: Vec<NeisMeal>) -> Result<usize> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            conn.transaction(|conn| {
+                for meal in &meals {
+                    let meal_id = Self::find_or_insert(conn, meal.date, meal.slot)?;
+                    diesel::update(dsl::meals.find(meal_id))
+                        .set(dsl::calories.eq(meal.calories))
+                        .execute(conn)?;
+
+                    let names = meal
+                        .dishes
+                        .iter()
+                        .map(|dish| dish.name.clone())
+                        .collect::<Vec<_>>();
+                    Self::replace_items(conn, meal_id, &names)?;
+
+                    for dish in &meal.dishes {
+                        Self::set_allergens(conn, meal_id, &dish.name, &dish.allergens)?;
+                    }
+
+                    Self::replace_nutrients(conn, meal_id, &meal.nutrients)?;
+                }
+
+                Ok::<_, diesel::result::Error>(meals.len())
+            })
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    fn find_or_insert(
+        conn: &mut MysqlConnection,
+        date: NaiveDate,
+        slot: MealSlot,
+    ) -> QueryResult<u64> {
+        let find = || {
+            dsl::meals
+                .filter(dsl::date.eq(date))
+                .filter(dsl::slot.eq(slot.code()))
+                .select(dsl::id)
+        };
+
+        match find().first::<u64>(conn).optional()? {
+            Some(id) => Ok(id),
+            None => {
+                diesel::insert_into(dsl::meals)
+                    .values((dsl::date.eq(date), dsl::slot.eq(slot.code())))
+                    .execute(conn)?;
+                find().first::<u64>(conn)
+            }
+        }
+    }
+
+    /// Makes `dishes` the items of a meal, in that order
+    /// * Names match existing items under the column's collation, as the unique key does, so
+    ///   "milk" takes over the item of "Milk" instead of colliding with it
+    fn replace_items(
+        conn: &mut MysqlConnection,
+        meal_id: u64,
+        dishes: &[String],
+    ) -> QueryResult<()> {
+        diesel::delete(
+            meal_items::table
+                .filter(meal_items::meal_id.eq(meal_id))
+                .filter(meal_items::name.ne_all(dishes)),
+        )
+        .execute(conn)?;
+
+        for (sort_order, name) in dishes.iter().enumerate() {
+            let sort_order = sort_order as u32;
+            // Counts matched rows, since diesel connects with CLIENT_FOUND_ROWS
+            let updated = diesel::update(
+                meal_items::table
+                    .filter(meal_items::meal_id.eq(meal_id))
+                    .filter(meal_items::name.eq(name)),
+            )
+            .set((
+                meal_items::name.eq(name),
+                meal_items::sort_order.eq(sort_order),
+            ))
+            .execute(conn)?;
+
+            if updated == 0 {
+                diesel::insert_into(meal_items::table)
+                    .values((
+                        meal_items::meal_id.eq(meal_id),
+                        meal_items::name.eq(name),
+                        meal_items::sort_order.eq(sort_order),
+                    ))
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_allergens(
+        conn: &mut MysqlConnection,
+        meal_id: u64,
+        name: &str,
+        allergens: &[u8],
+    ) -> QueryResult<()> {
+        diesel::update(
+            meal_items::table
+                .filter(meal_items::meal_id.eq(meal_id))
+                .filter(meal_items::name.eq(name)),
+        )
+        .set(meal_items::allergens.eq(allergen::join_codes(allergens)))
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn replace_nutrients(
+        conn: &mut MysqlConnection,
+        meal_id: u64,
+        nutrients: &[NeisNutrient],
+    ) -> QueryResult<()> {
+        let names = nutrients
+            .iter()
+            .map(|nutrient| nutrient.name.as_str())
+            .collect::<Vec<_>>();
+        diesel::delete(
+            meal_nutrients::table
+                .filter(meal_nutrients::meal_id.eq(meal_id))
+                .filter(meal_nutrients::name.ne_all(&names)),
+        )
+        .execute(conn)?;
+
+        let existing = meal_nutrients::table
+            .filter(meal_nutrients::meal_id.eq(meal_id))
+            .select(meal_nutrients::name)
+            .load::<String>(conn)?;
+
+        for nutrient in nutrients {
+            if existing.contains(&nutrient.name) {
+                diesel::update(
+                    meal_nutrients::table
+                        .filter(meal_nutrients::meal_id.eq(meal_id))
+                        .filter(meal_nutrients::name.eq(&nutrient.name)),
+                )
+                .set((
+                    meal_nutrients::unit.eq(&nutrient.unit),
+                    meal_nutrients::amount.eq(nutrient.amount),
+                ))
+                .execute(conn)?;
+            } else {
+                diesel::insert_into(meal_nutrients::table)
+                    .values((
+                        meal_nutrients::meal_id.eq(meal_id),
+                        meal_nutrients::name.eq(&nutrient.name),
+                        meal_nutrients::unit.eq(&nutrient.unit),
+                        meal_nutrients::amount.eq(nutrient.amount),
+                    ))
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the menu of a meal with its dishes, returning whether there was one
+    pub async fn delete(pool: Data<DbPool>, date: NaiveDate, slot: MealSlot) -> Result<bool> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            diesel::delete(
+                dsl::meals
+                    .filter(dsl::date.eq(date))
+                    .filter(dsl::slot.eq(slot.code())),
+            )
+            .execute(&mut conn)
+        })
+        .await?
+        .map(|deleted| deleted > 0)
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+
+    fn items(conn: &mut MysqlConnection, meal_id: u64) -> Vec<(u64, String)> {
+        meal_items::table
+            .filter(meal_items::meal_id.eq(meal_id))
+            .order(meal_items::sort_order.asc())
+            .select((meal_items::id, meal_items::name))
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a migrated MySQL database in UNHOMEAL_TEST_DATABASE_URL"]
+    fn reposts_a_dish_differing_only_in_case() {
+        let mut conn = test_connection();
+        let date = NaiveDate::from_ymd(2026, 10, 19);
+        let meal_id = MealDAO::find_or_insert(&mut conn, date, MealSlot::Lunch).unwrap();
+
+        let dishes = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        MealDAO::replace_items(&mut conn, meal_id, &dishes(&["Rice", "Milk"])).unwrap();
+        let before = items(&mut conn, meal_id);
+
+        MealDAO::replace_items(&mut conn, meal_id, &dishes(&["milk", "rice", "Kimchi"])).unwrap();
+        let after = items(&mut conn, meal_id);
+
+        assert_eq!(
+            after
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["milk", "rice", "Kimchi"]
+        );
+        // Ratings keep pointing at the same items
+        assert_eq!(after[0].0, before[1].0);
+        assert_eq!(after[1].0, before[0].0);
+    }
 }
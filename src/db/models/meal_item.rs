@@ -0,0 +1,86 @@
+use crate::{
+    allergen,
+    db::{
+        get_conn,
+        models::meal::MealSlot,
+        schema::{
+            meal_items::{self, dsl},
+            meals,
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+/// A dish on the menu of a meal
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = meal_items)]
+pub struct MealItemDAO {
+    pub id: u64,
+    pub meal_id: u64,
+    pub name: String,
+    /// Comma separated NEIS allergen codes, e.g. "5,9,13"
+    pub allergens: String,
+    pub sort_order: u32,
+}
+
+impl MealItemDAO {
+    pub fn allergen_codes(&self) -> Vec<u8> {
+        allergen::parse_codes(&self.allergens)
+    }
+
+    /// Dishes served on a date in every meal, in menu order
+    pub async fn by_date(pool: Data<DbPool>, date: NaiveDate) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meal_items
+                .inner_join(meals::table)
+                .filter(meals::date.eq(date))
+                .select(meal_items::all_columns)
+                .order((meals::slot.asc(), dsl::sort_order.asc(), dsl::id.asc()))
+                .load::<MealItemDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Dishes of one meal, in menu order
+    pub async fn by_meal(pool: Data<DbPool>, date: NaiveDate, slot: MealSlot) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meal_items
+                .inner_join(meals::table)
+                .filter(meals::date.eq(date))
+                .filter(meals::slot.eq(slot.code()))
+                .select(meal_items::all_columns)
+                .order((dsl::sort_order.asc(), dsl::id.asc()))
+                .load::<MealItemDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Dishes served from `from` to `to` (both inclusive), in menu order
+    pub async fn by_range(pool: Data<DbPool>, from: NaiveDate, to: NaiveDate) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meal_items
+                .inner_join(meals::table)
+                .filter(meals::date.ge(from))
+                .filter(meals::date.le(to))
+                .select(meal_items::all_columns)
+                .order((
+                    meals::date.asc(),
+                    meals::slot.asc(),
+                    dsl::sort_order.asc(),
+                    dsl::id.asc(),
+                ))
+                .load::<MealItemDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    db::{
+        get_conn,
+        schema::{
+            meal_nutrients::{self, dsl},
+            meals,
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// Nutrient content of a meal, from the NEIS NTR_INFO
+#[derive(Clone, Queryable, Insertable, Serialize)]
+#[diesel(table_name = meal_nutrients)]
+pub struct MealNutrientDAO {
+    #[serde(skip)]
+    pub id: u64,
+    #[serde(skip)]
+    pub meal_id: u64,
+    pub name: String,
+    pub unit: String,
+    pub amount: f32,
+}
+
+impl MealNutrientDAO {
+    /// Nutrients of meals served from `from` to `to` (both inclusive)
+    pub async fn by_range(pool: Data<DbPool>, from: NaiveDate, to: NaiveDate) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meal_nutrients
+                .inner_join(meals::table)
+                .filter(meals::date.ge(from))
+                .filter(meals::date.le(to))
+                .select(meal_nutrients::all_columns)
+                .order(dsl::id.asc())
+                .load::<MealNutrientDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    clock,
+    db::{
+        get_conn,
+        schema::meal_prices::{self, dsl},
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use super::meal::MealSlot;
+
+/// Price of a meal slot from `starts_on` until the next price of the slot
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = meal_prices)]
+pub struct MealPriceDAO {
+    pub id: u64,
+    pub starts_on: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
+    /// In won
+    pub price: u32,
+    pub created_by: Option<u64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl MealPriceDAO {
+    /// Every price starting on or before `to`, oldest first
+    pub async fn up_to(pool: Data<DbPool>, to: NaiveDate) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::meal_prices
+                .filter(dsl::starts_on.le(to))
+                .order((dsl::starts_on.asc(), dsl::slot.asc()))
+                .load::<MealPriceDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Sets the price of the slot from `starts_on`, replacing one set for the same day
+    pub async fn set(
+        pool: Data<DbPool>,
+        starts_on: NaiveDate,
+        slot: MealSlot,
+        price: u32,
+        created_by: u64,
+    ) -> Result<()> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            conn.transaction(|conn| {
+                let updated = diesel::update(
+                    dsl::meal_prices
+                        .filter(dsl::starts_on.eq(starts_on))
+                        .filter(dsl::slot.eq(slot.code())),
+                )
+                .set((
+                    dsl::price.eq(price),
+                    dsl::created_by.eq(Some(created_by)),
+                    dsl::created_at.eq(now),
+                ))
+                .execute(conn)?;
+
+                if updated == 0 {
+                    diesel::insert_into(dsl::meal_prices)
+                        .values((
+                            dsl::starts_on.eq(starts_on),
+                            dsl::slot.eq(slot.code()),
+                            dsl::price.eq(price),
+                            dsl::created_by.eq(Some(created_by)),
+                            dsl::created_at.eq(now),
+                        ))
+                        .execute(conn)?;
+                }
+                Ok::<_, diesel::result::Error>(())
+            })
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    pub fn meal_slot(&self) -> MealSlot {
+        MealSlot::from_code(self.slot).unwrap_or_default()
+    }
+}
@@ -1,22 +1,53 @@
 use crate::{
+    clock,
     db::{
         get_conn,
+        models::{
+            meal_item::MealItemDAO,
+            survey_answer::SurveyAnswerDAO,
+            total_rate::{TotalRateDAO, MAX_TOTAL_RATE},
+        },
+        retry_on_deadlock,
         schema::{
+            meal_items, meals,
             rates::{self, dsl},
             users,
         },
         DbPool, UserDAO,
     },
     error::{Error, Result},
-    routes::{GetUserRatesParam, Rate, RateParam},
+    routes::{GetUserRatesParam, Rate, RateLevel, RateParam},
+    survey::Submission,
 };
 use actix_web::web::{block, Data};
-use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
-use diesel::prelude::*;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::{
+    mysql::Mysql,
+    prelude::*,
+    sql_types::{BigInt, Datetime, Nullable, TinyInt, Unsigned, Varchar},
+    MysqlConnection,
+};
+use serde::Serialize;
 
 #[derive(Clone, Queryable, Insertable)]
 #[diesel(table_name = rates)]
 pub struct RateDAO {
+    pub id: u64,
+    pub user_id: u64,
+    /// None once the dish is withdrawn from the menu
+    pub meal_item_id: Option<u64>,
+    /// Name of the dish when it was rated
+    pub food_name: String,
+    pub rate_level: i8,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub comment: Option<String>,
+}
+
+/// A rate `repair-rates` reports
+/// * Only columns that exist before the check_rate_levels migration, which waits for the repair
+#[derive(Clone, Queryable)]
+pub struct InvalidRate {
     pub id: u64,
     pub user_id: u64,
     pub food_name: String,
@@ -26,11 +57,15 @@ pub struct RateDAO {
 
 impl RateDAO {
     pub async fn get_today(pool: Data<DbPool>) -> Result<Vec<Rate>> {
-        Self::get(pool, Local::today().naive_local()).await
+        Self::get(pool, clock::today()).await
     }
 
-    pub async fn get_one_today(pool: Data<DbPool>, param: GetUserRatesParam) -> Result<Vec<Rate>> {
-        Self::get_one(pool, param, Local::today().naive_local()).await
+    pub async fn get_one_today(
+        pool: Data<DbPool>,
+        user: &UserDAO,
+        param: GetUserRatesParam,
+    ) -> Result<Vec<Rate>> {
+        Self::get_one(pool, user, param, clock::today()).await
     }
 
     pub async fn get(pool: Data<DbPool>, date: NaiveDate) -> Result<Vec<Rate>> {
@@ -42,25 +77,34 @@ impl RateDAO {
                 .left_join(users::table.on(users::id.eq(dsl::user_id)))
                 .select((
                     users::username.assume_not_null(),
+                    rates::meal_item_id,
                     rates::food_name,
                     rates::rate_level,
                     rates::created_at,
+                    rates::comment,
                 ))
                 .filter(rates::created_at.ge(date.and_hms(0, 0, 0)))
                 .filter(rates::created_at.lt(next_date.and_hms(0, 0, 0)))
-                .get_results::<(String, String, i8, NaiveDateTime)>(&mut conn)
+                .get_results::<(
+                    String,
+                    Option<u64>,
+                    String,
+                    i8,
+                    NaiveDateTime,
+                    Option<String>,
+                )>(&mut conn)
         })
         .await?
         .map_err(Error::not_found_on_db)
-        .map(|v| v.iter().map(|elem| elem.into()).collect::<Vec<_>>())
+        .and_then(|v| v.iter().map(Rate::try_from).collect())
     }
 
     pub async fn get_one(
         pool: Data<DbPool>,
+        user: &UserDAO,
         param: GetUserRatesParam,
         date: NaiveDate,
     ) -> Result<Vec<Rate>> {
-        let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
         let target = UserDAO::by_username(pool.clone(), &param.username).await?;
 
         if user.is_teacher || user.id == target.id {
@@ -71,43 +115,312 @@ impl RateDAO {
                     .left_join(users::table.on(users::id.eq(dsl::user_id)))
                     .select((
                         users::username.assume_not_null(),
+                        rates::meal_item_id,
                         rates::food_name,
                         rates::rate_level,
                         rates::created_at,
+                        rates::comment,
                     ))
                     .filter(dsl::created_at.ge(date.and_hms(0, 0, 0)))
                     .filter(dsl::created_at.lt((date + Duration::days(1)).and_hms(0, 0, 0)))
                     .filter(dsl::user_id.eq(target.id))
-                    .load::<(String, String, i8, NaiveDateTime)>(&mut conn)
+                    .load::<(
+                        String,
+                        Option<u64>,
+                        String,
+                        i8,
+                        NaiveDateTime,
+                        Option<String>,
+                    )>(&mut conn)
             })
             .await?
             .map_err(Into::into)
-            .map(|v| v.iter().map(|elem| elem.into()).collect::<Vec<_>>())
+            .and_then(|v| v.iter().map(Rate::try_from).collect())
         } else {
             Err(Error::Unprivileged)
         }
     }
 
-    pub async fn post(pool: Data<DbPool>, rate_param: RateParam) -> Result<()> {
-        let user = UserDAO::by_session_id(pool.clone(), rate_param.session_id).await?;
+    /// Rates given from `from` to `to` with their users, and the slot of the rated meal if known
+    pub async fn with_users(
+        pool: Data<DbPool>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(RateDAO, UserDAO, Option<u8>)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::rates
+                .inner_join(users::table.on(users::id.eq(dsl::user_id)))
+                .left_join(meal_items::table.inner_join(meals::table))
+                .filter(dsl::created_at.ge(from.and_hms(0, 0, 0)))
+                .filter(dsl::created_at.lt((to + Duration::days(1)).and_hms(0, 0, 0)))
+                .select((
+                    rates::all_columns,
+                    users::all_columns,
+                    meals::slot.nullable(),
+                ))
+                .order((dsl::created_at.asc(), users::username.asc()))
+                .load::<(RateDAO, UserDAO, Option<u8>)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
 
-        if rate_param.send_date != Local::today().naive_local() {
-            Err(Error::DateChanged)
-        } else {
-            for r in rate_param.rates.clone() {
-                let mut conn = get_conn(pool.clone()).await;
-                block(move || {
-                    diesel::insert_into(dsl::rates)
-                        .values((
-                            dsl::food_name.eq(r.food_name),
-                            dsl::rate_level.eq(r.level as i8),
-                            dsl::user_id.eq(&user.id),
-                        ))
-                        .execute(&mut conn)
-                })
-                .await??;
+    /// Rates whose rate_level is no RateLevel, oldest first
+    pub async fn invalid_levels(pool: Data<DbPool>) -> Result<Vec<InvalidRate>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::rates
+                .filter(dsl::rate_level.ne_all(VALID_LEVELS))
+                .order(dsl::id.asc())
+                .select((
+                    dsl::id,
+                    dsl::user_id,
+                    dsl::food_name,
+                    dsl::rate_level,
+                    dsl::created_at,
+                ))
+                .load::<InvalidRate>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Sets the rate_level of every `(id, rate_level)` in one transaction
+    pub async fn set_levels(pool: Data<DbPool>, levels: Vec<(u64, i8)>) -> Result<()> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            conn.transaction(|conn| {
+                for (id, rate_level) in levels {
+                    diesel::update(dsl::rates.find(id))
+                        .set(dsl::rate_level.eq(rate_level))
+                        .execute(conn)?;
+                }
+                Ok::<_, diesel::result::Error>(())
+            })
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Rates dishes of today's menu and the day as a whole, in one transaction
+    /// * Every meal_item_id must be served on send_date, and rated at most once per request
+    /// * Answers to the active survey, checked beforehand, are saved in the same transaction
+    /// * Nothing is saved unless every rating is valid and written
+    /// * Resubmitting updates the user's earlier ratings instead of adding more
+    pub async fn post(
+        pool: Data<DbPool>,
+        user: &UserDAO,
+        rate_param: RateParam,
+        survey: Option<Submission>,
+    ) -> Result<RateOutcome> {
+        let user_id = user.id;
+
+        if rate_param.send_date != clock::today() {
+            return Err(Error::DateChanged);
+        }
+        if rate_param.rates.is_empty() && rate_param.total_rate.is_none() && survey.is_none() {
+            return Err(Error::InvalidParam(String::from(
+                "Give `rates`, `total_rate` or `answers`",
+            )));
+        }
+        if rate_param.comment.is_some() && rate_param.total_rate.is_none() {
+            return Err(Error::InvalidParam(String::from(
+                "`comment` needs a `total_rate`",
+            )));
+        }
+        if rate_param
+            .total_rate
+            .is_some_and(|total_rate| total_rate > MAX_TOTAL_RATE)
+        {
+            return Err(Error::InvalidParam(format!(
+                "`total_rate` must be from 0 to {}",
+                MAX_TOTAL_RATE
+            )));
+        }
+
+        let items = MealItemDAO::by_date(pool.clone(), rate_param.send_date).await?;
+
+        let mut rates = Vec::new();
+        for r in &rate_param.rates {
+            let food_name = match items.iter().find(|item| item.id == r.meal_item_id) {
+                Some(item) => item.name.clone(),
+                None => {
+                    return Err(Error::InvalidParam(format!(
+                        "Dish {} is not on the menu of {}",
+                        r.meal_item_id, rate_param.send_date
+                    )))
+                }
+            };
+            if rates
+                .iter()
+                .any(|rating: &DishRating| rating.meal_item_id == r.meal_item_id)
+            {
+                return Err(Error::InvalidParam(format!(
+                    "Dish {} is rated more than once",
+                    r.meal_item_id
+                )));
             }
-            Ok(())
+            rates.push(DishRating {
+                meal_item_id: r.meal_item_id,
+                food_name,
+                rate_level: r.level.clone() as i8,
+                comment: r.comment.clone(),
+            });
+        }
+
+        let rated_on = rate_param.send_date;
+        let total_rate = rate_param.total_rate;
+        let total_comment = rate_param.comment;
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                let rated = Self::upsert(conn, user_id, &rates, now)?;
+                let total_rate = match total_rate {
+                    Some(total_rate) => Some(Upsert::from_created(TotalRateDAO::upsert(
+                        conn,
+                        user_id,
+                        rated_on,
+                        total_rate,
+                        total_comment.as_deref(),
+                        now,
+                    )?)),
+                    None => None,
+                };
+                let survey = match &survey {
+                    Some(survey) => Some(Upsert::from_created(SurveyAnswerDAO::replace(
+                        conn,
+                        user_id,
+                        survey.meal_date,
+                        survey.slot,
+                        &survey.question_ids,
+                        &survey.answers,
+                        now,
+                    )?)),
+                    None => None,
+                };
+
+                Ok(RateOutcome::new(rated, total_rate, survey))
+            })
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Sets the user's ratings of dishes in the caller's transaction
+    /// * One INSERT … ON DUPLICATE KEY UPDATE on rates_user_meal_item, which takes no gap locks
+    ///   for dishes not rated yet, so concurrent first submissions don't deadlock
+    fn upsert(
+        conn: &mut MysqlConnection,
+        user_id: u64,
+        rates: &[DishRating],
+        now: NaiveDateTime,
+    ) -> QueryResult<Vec<RatedDish>> {
+        if rates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Only tells created from updated in the response, so a plain read is enough
+        let existing = dsl::rates
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::meal_item_id.eq_any(rates.iter().map(|rating| rating.meal_item_id)))
+            .select(dsl::meal_item_id)
+            .load::<Option<u64>>(conn)?;
+
+        let mut query = diesel::sql_query(format!(
+            "INSERT INTO rates (user_id, meal_item_id, food_name, rate_level, comment, created_at) \
+             VALUES {} \
+             ON DUPLICATE KEY UPDATE food_name = VALUES(food_name), \
+             rate_level = VALUES(rate_level), comment = VALUES(comment), \
+             updated_at = VALUES(created_at)",
+            vec!["(?, ?, ?, ?, ?, ?)"; rates.len()].join(", ")
+        ))
+        .into_boxed::<Mysql>();
+        for rating in rates {
+            query = query
+                .bind::<Unsigned<BigInt>, _>(user_id)
+                .bind::<Unsigned<BigInt>, _>(rating.meal_item_id)
+                .bind::<Varchar, _>(&rating.food_name)
+                .bind::<TinyInt, _>(rating.rate_level)
+                .bind::<Nullable<Varchar>, _>(&rating.comment)
+                .bind::<Datetime, _>(now);
+        }
+        query.execute(conn)?;
+
+        Ok(rates
+            .iter()
+            .map(|rating| RatedDish {
+                meal_item_id: rating.meal_item_id,
+                status: Upsert::from_created(!existing.contains(&Some(rating.meal_item_id))),
+            })
+            .collect())
+    }
+}
+
+/// A checked rating of a dish on the menu
+struct DishRating {
+    meal_item_id: u64,
+    /// Name of the dish when it was rated
+    food_name: String,
+    rate_level: i8,
+    comment: Option<String>,
+}
+
+/// Stored values of RateLevel, also enforced by the rates_rate_level CHECK constraint
+const VALID_LEVELS: [i8; 3] = [
+    RateLevel::Bad as i8,
+    RateLevel::Soso as i8,
+    RateLevel::Good as i8,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Upsert {
+    Created,
+    Updated,
+}
+
+impl Upsert {
+    fn from_created(created: bool) -> Self {
+        if created {
+            Self::Created
+        } else {
+            Self::Updated
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RatedDish {
+    pub meal_item_id: u64,
+    pub status: Upsert,
+}
+
+/// What a rating submission changed
+#[derive(Serialize)]
+pub struct RateOutcome {
+    /// Updated when the user had already rated anything submitted again
+    pub status: Upsert,
+    pub rates: Vec<RatedDish>,
+    /// None when no total rating was given
+    pub total_rate: Option<Upsert>,
+    /// Answers to the active survey, None when none were given
+    pub survey: Option<Upsert>,
+}
+
+impl RateOutcome {
+    fn new(rates: Vec<RatedDish>, total_rate: Option<Upsert>, survey: Option<Upsert>) -> Self {
+        let updated = total_rate == Some(Upsert::Updated)
+            || survey == Some(Upsert::Updated)
+            || rates.iter().any(|dish| dish.status == Upsert::Updated);
+
+        Self {
+            status: Upsert::from_created(!updated),
+            rates,
+            total_rate,
+            survey,
         }
     }
 }
@@ -0,0 +1,267 @@
+use crate::{
+    clock,
+    db::{
+        get_conn,
+        schema::sessions::{self, dsl},
+        DbPool,
+    },
+    error::{Error, Result},
+    token::UserToken,
+};
+use actix_web::web::{block, Data};
+use chrono::{Duration, NaiveDateTime};
+use diesel::{prelude::*, MysqlConnection};
+use serde::{Deserialize, Serialize};
+
+/// `session` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Session ends after this long without any request
+    pub idle_timeout_secs: i64,
+    /// Session ends this long after login, however active it is
+    pub absolute_timeout_secs: i64,
+    /// When false, logging in while another session is alive fails with AlreadyLoggedIn
+    pub allow_concurrent: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 60 * 60 * 24 * 7,
+            absolute_timeout_secs: 60 * 60 * 24 * 30,
+            allow_concurrent: true,
+        }
+    }
+}
+
+#[derive(Clone, Queryable, Insertable, Serialize)]
+#[diesel(table_name = sessions)]
+pub struct SessionDAO {
+    pub id: String,
+    pub user_id: u64,
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl SessionDAO {
+    /// Idle expiry, capped by the absolute expiry
+    fn next_expiry(&self, config: &SessionConfig, now: NaiveDateTime) -> NaiveDateTime {
+        let idle = now + Duration::seconds(config.idle_timeout_secs);
+        let absolute = self.created_at + Duration::seconds(config.absolute_timeout_secs);
+
+        idle.min(absolute)
+    }
+
+    pub async fn create(
+        pool: Data<DbPool>,
+        config: &SessionConfig,
+        user_id: u64,
+        device: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<Self> {
+        let now = clock::now();
+        let mut session = SessionDAO {
+            id: UserToken::new_session_id(),
+            user_id,
+            device,
+            user_agent,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now,
+        };
+        session.expires_at = session.next_expiry(config, now);
+
+        let mut conn = get_conn(pool).await;
+        let new_session = session.clone();
+        block(move || {
+            diesel::insert_into(dsl::sessions)
+                .values(&new_session)
+                .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(session)
+    }
+
+    /// Active sessions of a user, most recently used first
+    pub async fn by_user(pool: Data<DbPool>, user_id: u64) -> Result<Vec<Self>> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::sessions
+                .filter(dsl::user_id.eq(user_id))
+                .filter(dsl::expires_at.gt(now))
+                .order(dsl::last_seen_at.desc())
+                .load::<SessionDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Looks up a live session and slides its idle expiry forward
+    /// * Expired sessions are deleted and reported as NoSuchSession
+    pub async fn touch<T>(pool: Data<DbPool>, config: &SessionConfig, id: T) -> Result<Self>
+    where
+        T: Into<String>,
+    {
+        let id: String = id.into();
+        let mut conn = get_conn(pool.clone()).await;
+        let sid = id.clone();
+        let mut session = block(move || dsl::sessions.find(sid).first::<SessionDAO>(&mut conn))
+            .await?
+            .map_err(Error::no_such_session)?;
+
+        let now = clock::now();
+        if session.expires_at <= now {
+            Self::revoke(pool, id).await?;
+            return Err(Error::NoSuchSession);
+        }
+
+        session.last_seen_at = now;
+        session.expires_at = session.next_expiry(config, now);
+
+        let mut conn = get_conn(pool).await;
+        let (last_seen_at, expires_at) = (session.last_seen_at, session.expires_at);
+        block(move || {
+            diesel::update(dsl::sessions.find(id))
+                .set((
+                    dsl::last_seen_at.eq(last_seen_at),
+                    dsl::expires_at.eq(expires_at),
+                ))
+                .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(session)
+    }
+
+    pub async fn revoke<T>(pool: Data<DbPool>, id: T) -> Result<()>
+    where
+        T: Into<String>,
+    {
+        let id: String = id.into();
+        let mut conn = get_conn(pool).await;
+        block(move || diesel::delete(dsl::sessions.find(id)).execute(&mut conn))
+            .await??;
+
+        Ok(())
+    }
+
+    /// Revokes a session only if it belongs to the user
+    pub async fn revoke_own<T>(pool: Data<DbPool>, user_id: u64, id: T) -> Result<()>
+    where
+        T: Into<String>,
+    {
+        let id: String = id.into();
+        let mut conn = get_conn(pool).await;
+        let deleted = block(move || {
+            diesel::delete(
+                dsl::sessions
+                    .filter(dsl::id.eq(id))
+                    .filter(dsl::user_id.eq(user_id)),
+            )
+            .execute(&mut conn)
+        })
+        .await??;
+
+        if deleted == 0 {
+            Err(Error::NoSuchSession)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deletes every session of a user but `keep`, e.g. once the password changed
+    pub fn revoke_others(
+        conn: &mut MysqlConnection,
+        user_id: u64,
+        keep: Option<&str>,
+    ) -> QueryResult<usize> {
+        let mut query = diesel::delete(dsl::sessions)
+            .filter(dsl::user_id.eq(user_id))
+            .into_boxed();
+        if let Some(keep) = keep {
+            query = query.filter(dsl::id.ne(keep.to_owned()));
+        }
+
+        query.execute(conn)
+    }
+
+    pub async fn remove_expired(pool: Data<DbPool>, user_id: u64) -> Result<()> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            diesel::delete(
+                dsl::sessions
+                    .filter(dsl::user_id.eq(user_id))
+                    .filter(dsl::expires_at.le(now)),
+            )
+            .execute(&mut conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{schema::users, test_connection};
+
+    fn insert_session(conn: &mut MysqlConnection, user_id: u64, id: &str) {
+        let now = clock::now();
+        diesel::insert_into(dsl::sessions)
+            .values(&SessionDAO {
+                id: id.to_owned(),
+                user_id,
+                device: None,
+                user_agent: None,
+                created_at: now,
+                last_seen_at: now,
+                expires_at: now + Duration::days(1),
+            })
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a migrated MySQL database in UNHOMEAL_TEST_DATABASE_URL"]
+    fn revoke_others_keeps_only_the_current_session() {
+        let mut conn = test_connection();
+        diesel::insert_into(users::table)
+            .values((
+                users::username.eq("session-test"),
+                users::name.eq("Session Test"),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        let user_id = users::table
+            .filter(users::username.eq("session-test"))
+            .select(users::id)
+            .first::<u64>(&mut conn)
+            .unwrap();
+        insert_session(&mut conn, user_id, "old-session");
+        insert_session(&mut conn, user_id, "current-session");
+
+        let revoked =
+            SessionDAO::revoke_others(&mut conn, user_id, Some("current-session")).unwrap();
+
+        assert_eq!(revoked, 1);
+        // touch reports a missing row as NoSuchSession
+        assert!(matches!(
+            dsl::sessions
+                .find("old-session")
+                .first::<SessionDAO>(&mut conn),
+            Err(diesel::result::Error::NotFound)
+        ));
+        assert!(dsl::sessions
+            .find("current-session")
+            .first::<SessionDAO>(&mut conn)
+            .is_ok());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    db::{
+        get_conn,
+        schema::{
+            survey_answers::{self, dsl},
+            users,
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{prelude::*, MysqlConnection};
+
+use super::{meal::MealSlot, user::UserDAO};
+
+/// A user's answer to a survey question about a meal, or a dish of it
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = survey_answers)]
+pub struct SurveyAnswerDAO {
+    pub id: u64,
+    pub question_id: u64,
+    pub user_id: u64,
+    pub meal_date: NaiveDate,
+    /// MealSlot code
+    pub slot: u8,
+    /// Dish of a per-dish question; None once the dish is withdrawn
+    pub meal_item_id: Option<u64>,
+    /// Name of the dish when it was answered
+    pub food_name: Option<String>,
+    /// Likert point, 1 for yes and 0 for no, or the index of the choice
+    pub number_value: Option<u8>,
+    pub text_value: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A validated answer ready to be saved
+pub struct NewAnswer {
+    pub question_id: u64,
+    pub meal_item_id: Option<u64>,
+    pub food_name: Option<String>,
+    pub number_value: Option<u8>,
+    pub text_value: Option<String>,
+}
+
+impl SurveyAnswerDAO {
+    /// Answers of a meal with their users, or of every meal of the day without a slot
+    pub async fn with_users(
+        pool: Data<DbPool>,
+        meal_date: NaiveDate,
+        slot: Option<MealSlot>,
+    ) -> Result<Vec<(Self, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            let mut query = dsl::survey_answers
+                .inner_join(users::table)
+                .filter(dsl::meal_date.eq(meal_date))
+                .order((users::username.asc(), dsl::question_id.asc(), dsl::id.asc()))
+                .into_boxed();
+            if let Some(slot) = slot {
+                query = query.filter(dsl::slot.eq(slot.code()));
+            }
+            query.load::<(SurveyAnswerDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Replaces the user's answers to `question_ids` for a meal in the caller's transaction
+    /// * Returns whether the user hadn't answered them before
+    pub fn replace(
+        conn: &mut MysqlConnection,
+        user_id: u64,
+        meal_date: NaiveDate,
+        slot: MealSlot,
+        question_ids: &[u64],
+        answers: &[NewAnswer],
+        now: NaiveDateTime,
+    ) -> QueryResult<bool> {
+        // Serializes submissions of the same user, which would otherwise both insert
+        users::table
+            .find(user_id)
+            .select(users::id)
+            .for_update()
+            .first::<u64>(conn)?;
+
+        let deleted = diesel::delete(
+            dsl::survey_answers
+                .filter(dsl::user_id.eq(user_id))
+                .filter(dsl::meal_date.eq(meal_date))
+                .filter(dsl::slot.eq(slot.code()))
+                .filter(dsl::question_id.eq_any(question_ids)),
+        )
+        .execute(conn)?;
+
+        let rows = answers
+            .iter()
+            .map(|answer| {
+                (
+                    dsl::question_id.eq(answer.question_id),
+                    dsl::user_id.eq(user_id),
+                    dsl::meal_date.eq(meal_date),
+                    dsl::slot.eq(slot.code()),
+                    dsl::meal_item_id.eq(answer.meal_item_id),
+                    dsl::food_name.eq(answer.food_name.as_deref()),
+                    dsl::number_value.eq(answer.number_value),
+                    dsl::text_value.eq(answer.text_value.as_deref()),
+                    dsl::created_at.eq(now),
+                )
+            })
+            .collect::<Vec<_>>();
+        if !rows.is_empty() {
+            diesel::insert_into(dsl::survey_answers)
+                .values(&rows)
+                .execute(conn)?;
+        }
+
+        Ok(deleted == 0)
+    }
+}
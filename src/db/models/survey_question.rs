@@ -0,0 +1,64 @@
+use crate::{
+    db::{
+        get_conn,
+        schema::survey_questions::{self, dsl},
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use diesel::prelude::*;
+
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = survey_questions)]
+pub struct SurveyQuestionDAO {
+    pub id: u64,
+    pub template_id: u64,
+    pub sort_order: u32,
+    /// QuestionKind code
+    pub kind: u8,
+    pub prompt: String,
+    /// Asked about every dish of the meal instead of the meal as a whole
+    pub per_dish: bool,
+    pub required: bool,
+    /// Highest point of a Likert question
+    pub scale: Option<u8>,
+    /// JSON array of the options of a multiple choice question
+    pub choices: Option<String>,
+}
+
+/// A question of a template being created
+pub struct NewQuestion {
+    pub kind: u8,
+    pub prompt: String,
+    pub per_dish: bool,
+    pub required: bool,
+    pub scale: Option<u8>,
+    pub choices: Option<String>,
+}
+
+impl SurveyQuestionDAO {
+    pub async fn by_ids(pool: Data<DbPool>, ids: Vec<u64>) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::survey_questions
+                .filter(dsl::id.eq_any(ids))
+                .load::<SurveyQuestionDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Questions of the templates, in template and question order
+    pub async fn by_templates(pool: Data<DbPool>, template_ids: Vec<u64>) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::survey_questions
+                .filter(dsl::template_id.eq_any(template_ids))
+                .order((dsl::template_id.asc(), dsl::sort_order.asc()))
+                .load::<SurveyQuestionDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    clock,
+    db::{
+        get_conn,
+        schema::{
+            survey_questions,
+            survey_templates::{self, dsl},
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use super::{meal::MealSlot, survey_question::NewQuestion};
+
+/// A version of a survey; its questions are SurveyQuestionDAO rows and never change
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = survey_templates)]
+pub struct SurveyTemplateDAO {
+    pub id: u64,
+    pub name: String,
+    /// 1 for the first template of the name, then one more for every revision
+    pub version: u32,
+    /// MealSlot code; None for every meal
+    pub slot: Option<u8>,
+    pub active_from: NaiveDate,
+    pub created_by: Option<u64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl SurveyTemplateDAO {
+    /// Template asked for a meal: the one that became active last,
+    /// preferring a template of the slot over one for every meal
+    pub async fn active(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+        slot: MealSlot,
+    ) -> Result<Option<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::survey_templates
+                .filter(dsl::active_from.le(date))
+                .filter(dsl::slot.eq(slot.code()).or(dsl::slot.is_null()))
+                .order((dsl::active_from.desc(), dsl::slot.desc(), dsl::id.desc()))
+                .first::<SurveyTemplateDAO>(&mut conn)
+                .optional()
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Every version of every template, latest version first
+    pub async fn all(pool: Data<DbPool>) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::survey_templates
+                .order((dsl::name.asc(), dsl::version.desc()))
+                .load::<SurveyTemplateDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    pub async fn by_ids(pool: Data<DbPool>, ids: Vec<u64>) -> Result<Vec<Self>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::survey_templates
+                .filter(dsl::id.eq_any(ids))
+                .order(dsl::id.asc())
+                .load::<SurveyTemplateDAO>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Saves the next version of the template `name` with its questions, in order
+    pub async fn create(
+        pool: Data<DbPool>,
+        name: String,
+        slot: Option<MealSlot>,
+        active_from: NaiveDate,
+        created_by: u64,
+        questions: Vec<NewQuestion>,
+    ) -> Result<Self> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            conn.transaction(|conn| {
+                let versions = dsl::survey_templates
+                    .filter(dsl::name.eq(&name))
+                    .select(dsl::version)
+                    .for_update()
+                    .load::<u32>(conn)?;
+                let version = versions.into_iter().max().unwrap_or(0) + 1;
+
+                diesel::insert_into(dsl::survey_templates)
+                    .values((
+                        dsl::name.eq(&name),
+                        dsl::version.eq(version),
+                        dsl::slot.eq(slot.map(MealSlot::code)),
+                        dsl::active_from.eq(active_from),
+                        dsl::created_by.eq(Some(created_by)),
+                        dsl::created_at.eq(now),
+                    ))
+                    .execute(conn)?;
+                let template = dsl::survey_templates
+                    .filter(dsl::name.eq(&name))
+                    .filter(dsl::version.eq(version))
+                    .first::<SurveyTemplateDAO>(conn)?;
+
+                let rows = questions
+                    .iter()
+                    .enumerate()
+                    .map(|(sort_order, question)| {
+                        (
+                            survey_questions::template_id.eq(template.id),
+                            survey_questions::sort_order.eq(sort_order as u32),
+                            survey_questions::kind.eq(question.kind),
+                            survey_questions::prompt.eq(&question.prompt),
+                            survey_questions::per_dish.eq(question.per_dish),
+                            survey_questions::required.eq(question.required),
+                            survey_questions::scale.eq(question.scale),
+                            survey_questions::choices.eq(question.choices.as_deref()),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(survey_questions::table)
+                    .values(&rows)
+                    .execute(conn)?;
+
+                Ok::<_, diesel::result::Error>(template)
+            })
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    pub fn meal_slot(&self) -> Option<MealSlot> {
+        self.slot.and_then(MealSlot::from_code)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    clock,
+    db::{
+        get_conn, retry_on_deadlock,
+        schema::{
+            suspensions::{self, dsl},
+            users,
+        },
+        DbPool,
+    },
+    error::Result,
+};
+use actix_web::web::{block, Data};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use super::user::UserDAO;
+
+/// A period the user can't apply for meals
+#[derive(Clone, Queryable, Insertable)]
+#[diesel(table_name = suspensions)]
+pub struct SuspensionDAO {
+    pub id: u64,
+    pub user_id: u64,
+    pub starts_on: NaiveDate,
+    /// Last suspended day
+    pub ends_on: NaiveDate,
+    /// No-shows that triggered it; 0 when a teacher imposed it
+    pub no_shows: u32,
+    /// Teacher who imposed it; None for the no-show policy
+    pub imposed_by: Option<u64>,
+    pub created_at: NaiveDateTime,
+    pub lifted_at: Option<NaiveDateTime>,
+    pub lifted_by: Option<u64>,
+}
+
+impl SuspensionDAO {
+    /// Last suspended day of each of the users suspended on `date`
+    pub async fn active_until(
+        pool: Data<DbPool>,
+        user_ids: Vec<u64>,
+        date: NaiveDate,
+    ) -> Result<HashMap<u64, NaiveDate>> {
+        let mut conn = get_conn(pool).await;
+        let rows = block(move || {
+            dsl::suspensions
+                .filter(dsl::user_id.eq_any(user_ids))
+                .filter(dsl::starts_on.le(date))
+                .filter(dsl::ends_on.ge(date))
+                .filter(dsl::lifted_at.is_null())
+                .group_by(dsl::user_id)
+                .select((dsl::user_id, diesel::dsl::max(dsl::ends_on)))
+                .load::<(u64, Option<NaiveDate>)>(&mut conn)
+        })
+        .await??;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(user_id, ends_on)| Some((user_id, ends_on?)))
+            .collect())
+    }
+
+    /// Day each of the users was last suspended on, lifted suspensions included
+    pub async fn last_imposed(
+        pool: Data<DbPool>,
+        user_ids: Vec<u64>,
+    ) -> Result<HashMap<u64, NaiveDate>> {
+        let mut conn = get_conn(pool).await;
+        let rows = block(move || {
+            dsl::suspensions
+                .filter(dsl::user_id.eq_any(user_ids))
+                .group_by(dsl::user_id)
+                .select((dsl::user_id, diesel::dsl::max(dsl::created_at)))
+                .load::<(u64, Option<NaiveDateTime>)>(&mut conn)
+        })
+        .await??;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(user_id, created_at)| Some((user_id, created_at?.date())))
+            .collect())
+    }
+
+    /// Suspensions in force on `date` with their users
+    pub async fn all_active(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+    ) -> Result<Vec<(SuspensionDAO, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::suspensions
+                .inner_join(users::table)
+                .filter(dsl::starts_on.le(date))
+                .filter(dsl::ends_on.ge(date))
+                .filter(dsl::lifted_at.is_null())
+                .order((users::username.asc(), dsl::ends_on.desc()))
+                .load::<(SuspensionDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Suspends users for their `(user_id, no_shows)` from `starts_on` through `ends_on`
+    /// * Locks the users first, so a concurrent check of the same user waits and then skips them
+    ///   as already suspended on `starts_on`
+    /// * With `disable_auto_apply`, also turns auto_apply off in the same transaction
+    pub async fn impose_for_no_shows(
+        pool: Data<DbPool>,
+        due: Vec<(u64, u32)>,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        disable_auto_apply: bool,
+    ) -> Result<()> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                let user_ids = due.iter().map(|&(user_id, _)| user_id).collect::<Vec<_>>();
+                users::table
+                    .filter(users::id.eq_any(&user_ids))
+                    .select(users::id)
+                    .for_update()
+                    .load::<u64>(conn)?;
+                let imposed = dsl::suspensions
+                    .filter(dsl::user_id.eq_any(&user_ids))
+                    .filter(dsl::created_at.ge(starts_on.and_hms(0, 0, 0)))
+                    .select(dsl::user_id)
+                    .load::<u64>(conn)?;
+
+                let due = due
+                    .iter()
+                    .filter(|(user_id, _)| !imposed.contains(user_id))
+                    .collect::<Vec<_>>();
+                if due.is_empty() {
+                    return Ok(());
+                }
+                let rows = due
+                    .iter()
+                    .map(|&&(user_id, no_shows)| {
+                        (
+                            dsl::user_id.eq(user_id),
+                            dsl::starts_on.eq(starts_on),
+                            dsl::ends_on.eq(ends_on),
+                            dsl::no_shows.eq(no_shows),
+                            dsl::created_at.eq(now),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(dsl::suspensions)
+                    .values(&rows)
+                    .execute(conn)?;
+
+                if disable_auto_apply {
+                    diesel::update(
+                        users::table
+                            .filter(users::id.eq_any(due.iter().map(|(user_id, _)| user_id))),
+                    )
+                    .set(users::auto_apply.eq(false))
+                    .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Lifts the user's suspensions in force from `today` on for the teacher, then suspends them
+    /// through `until` if given
+    /// * Both happen in one transaction with the user locked, like `impose_for_no_shows`
+    pub async fn replace(
+        pool: Data<DbPool>,
+        user_id: u64,
+        today: NaiveDate,
+        until: Option<NaiveDate>,
+        teacher_id: u64,
+    ) -> Result<()> {
+        let now = clock::now();
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            retry_on_deadlock(&mut conn, |conn| {
+                users::table
+                    .find(user_id)
+                    .select(users::id)
+                    .for_update()
+                    .first::<u64>(conn)?;
+
+                diesel::update(
+                    dsl::suspensions
+                        .filter(dsl::user_id.eq(user_id))
+                        .filter(dsl::ends_on.ge(today))
+                        .filter(dsl::lifted_at.is_null()),
+                )
+                .set((
+                    dsl::lifted_at.eq(Some(now)),
+                    dsl::lifted_by.eq(Some(teacher_id)),
+                ))
+                .execute(conn)?;
+
+                if let Some(until) = until {
+                    diesel::insert_into(dsl::suspensions)
+                        .values((
+                            dsl::user_id.eq(user_id),
+                            dsl::starts_on.eq(today),
+                            dsl::ends_on.eq(until),
+                            dsl::no_shows.eq(0),
+                            dsl::imposed_by.eq(Some(teacher_id)),
+                            dsl::created_at.eq(now),
+                        ))
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await??;
+
+        Ok(())
+    }
+}
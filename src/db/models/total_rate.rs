@@ -1,16 +1,26 @@
 use crate::{
+    clock,
     db::{
         get_conn,
         models::user::UserDAO,
-        schema::total_rates::{self, dsl},
+        schema::{
+            total_rates::{self, dsl},
+            users,
+        },
         DbPool,
     },
     error::{Error, Result},
-    routes::{GetUserRatesParam, RateParam},
+    routes::GetUserRatesParam,
 };
 use actix_web::web::{block, Data};
-use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
-use diesel::prelude::*;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{
+    prelude::*,
+    sql_types::{BigInt, Date, Datetime, Nullable, TinyInt, Unsigned, Varchar},
+    MysqlConnection,
+};
+
+pub const MAX_TOTAL_RATE: u8 = 10;
 
 #[derive(Clone, Queryable, Insertable)]
 #[diesel(table_name = total_rates)]
@@ -18,18 +28,32 @@ pub struct TotalRateDAO {
     pub id: u64,
     pub user_id: u64,
     pub rate_level: u8,
+    /// Day the rating is for; one per user
+    pub rated_on: NaiveDate,
     pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    /// About the meal as a whole
+    pub comment: Option<String>,
+}
+
+/// A total rating `repair-rates` reports
+/// * Only columns that exist before the check_rate_levels migration, which waits for the repair
+#[derive(Clone, Queryable)]
+pub struct OutOfRangeTotalRate {
+    pub id: u64,
+    pub user_id: u64,
+    pub rated_on: NaiveDate,
+    pub rate_level: u8,
 }
 
 impl TotalRateDAO {
     pub async fn avg_today(pool: Data<DbPool>) -> Result<f32> {
         let mut conn = get_conn(pool).await;
 
-        let today = Local::today().naive_local();
+        let today = clock::today();
         let result = block(move || {
             dsl::total_rates
-                .filter(dsl::created_at.ge(today.and_hms(0, 0, 0)))
-                .filter(dsl::created_at.lt((today + Duration::days(1)).and_hms(0, 0, 0)))
+                .filter(dsl::rated_on.eq(today))
                 .load::<TotalRateDAO>(&mut conn)
         })
         .await?
@@ -45,16 +69,20 @@ impl TotalRateDAO {
         Ok(total_avg)
     }
 
-    pub async fn get_one_today(pool: Data<DbPool>, param: GetUserRatesParam) -> Result<u8> {
-        Self::get_one(pool, param, Local::today().naive_local()).await
+    pub async fn get_one_today(
+        pool: Data<DbPool>,
+        user: &UserDAO,
+        param: GetUserRatesParam,
+    ) -> Result<u8> {
+        Self::get_one(pool, user, param, clock::today()).await
     }
 
     pub async fn get_one(
         pool: Data<DbPool>,
+        user: &UserDAO,
         param: GetUserRatesParam,
         date: NaiveDate,
     ) -> Result<u8> {
-        let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
         let target = UserDAO::by_username(pool.clone(), &param.username).await?;
 
         if user.is_teacher || user.id == target.id {
@@ -62,9 +90,8 @@ impl TotalRateDAO {
             block(move || {
                 dsl::total_rates
                     .select(dsl::rate_level)
-                    .filter(dsl::user_id.eq(&user.id))
-                    .filter(dsl::created_at.ge(date.and_hms(0, 0, 0)))
-                    .filter(dsl::created_at.lt((date + Duration::days(1)).and_hms(0, 0, 0)))
+                    .filter(dsl::user_id.eq(target.id))
+                    .filter(dsl::rated_on.eq(date))
                     .first::<u8>(&mut conn)
             })
             .await?
@@ -74,20 +101,80 @@ impl TotalRateDAO {
         }
     }
 
-    pub async fn post(pool: Data<DbPool>, rate_param: RateParam) -> Result<()> {
-        let user = UserDAO::by_session_id(pool.clone(), rate_param.session_id.clone()).await?;
+    /// Total ratings of a day that carry a comment, with their users
+    pub async fn with_comments(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+    ) -> Result<Vec<(Self, UserDAO)>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::total_rates
+                .inner_join(users::table.on(users::id.eq(dsl::user_id)))
+                .filter(dsl::rated_on.eq(date))
+                .filter(dsl::comment.is_not_null())
+                .order(dsl::created_at.asc())
+                .load::<(TotalRateDAO, UserDAO)>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
+
+    /// Total ratings above MAX_TOTAL_RATE, oldest first
+    pub async fn out_of_range(pool: Data<DbPool>) -> Result<Vec<OutOfRangeTotalRate>> {
+        let mut conn = get_conn(pool).await;
+        block(move || {
+            dsl::total_rates
+                .filter(dsl::rate_level.gt(MAX_TOTAL_RATE))
+                .order(dsl::id.asc())
+                .select((dsl::id, dsl::user_id, dsl::rated_on, dsl::rate_level))
+                .load::<OutOfRangeTotalRate>(&mut conn)
+        })
+        .await?
+        .map_err(Into::into)
+    }
 
+    /// Lowers total ratings above MAX_TOTAL_RATE to it; returns how many changed
+    pub async fn clamp(pool: Data<DbPool>) -> Result<usize> {
         let mut conn = get_conn(pool).await;
         block(move || {
-            diesel::insert_into(dsl::total_rates)
-                .values((
-                    dsl::user_id.eq(&user.id),
-                    dsl::rate_level.eq(rate_param.total_rate),
-                ))
+            diesel::update(dsl::total_rates.filter(dsl::rate_level.gt(MAX_TOTAL_RATE)))
+                .set(dsl::rate_level.eq(MAX_TOTAL_RATE))
                 .execute(&mut conn)
         })
         .await?
         .map_err(Into::into)
-        .map(|_| ())
+    }
+
+    /// Sets the user's total rating of `rated_on` in the caller's transaction
+    /// * Returns whether it was created rather than updated
+    /// * Upserts on total_rates_user_day like RateDAO does, without gap locks
+    pub fn upsert(
+        conn: &mut MysqlConnection,
+        user_id: u64,
+        rated_on: NaiveDate,
+        rate_level: u8,
+        comment: Option<&str>,
+        now: NaiveDateTime,
+    ) -> QueryResult<bool> {
+        let existing = dsl::total_rates
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::rated_on.eq(rated_on))
+            .select(dsl::id)
+            .first::<u64>(conn)
+            .optional()?;
+
+        diesel::sql_query(
+            "INSERT INTO total_rates (user_id, rate_level, rated_on, comment, created_at) \
+             VALUES (?, ?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE rate_level = VALUES(rate_level), \
+             comment = VALUES(comment), updated_at = VALUES(created_at)",
+        )
+        .bind::<Unsigned<BigInt>, _>(user_id)
+        .bind::<Unsigned<TinyInt>, _>(rate_level)
+        .bind::<Date, _>(rated_on)
+        .bind::<Nullable<Varchar>, _>(comment)
+        .bind::<Datetime, _>(now)
+        .execute(conn)?;
+        Ok(existing.is_none())
     }
 }
@@ -1,14 +1,15 @@
 use crate::{
+    allergen,
     db::{
         get_conn,
+        models::session::{SessionConfig, SessionDAO},
         schema::users::{self, dsl},
     },
     error::{Error, Result},
-    token::UserToken,
+    password::{check_password_strength, hash_password, verify_password},
     DbPool,
 };
 use actix_web::web::{block, Data};
-use anyhow::anyhow;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 
@@ -18,109 +19,176 @@ pub struct UserDAO {
     pub id: u64,
     pub username: String,
     pub name: String,
-    //password_hash: Binary
-    pub session_id: Option<String>,
+    /// Homeroom of a student; None for teachers
+    pub grade: Option<u8>,
+    pub class: Option<u8>,
+    pub password_hash: Option<String>,
 
+    /// Comma separated allergen codes, e.g. "1,5"
+    pub allergies: String,
     pub auto_apply: bool,
     pub is_teacher: bool,
     pub created_at: NaiveDateTime,
 }
 
 impl UserDAO {
-    pub async fn login(pool: Data<DbPool>, user_id: String) -> Result<(UserDAO, bool)> {
-        let mut user = Self::by_username(pool.clone(), user_id).await
-        .map_err(|e| {
-            if let Error::NotFoundOnDB = e {
-                Error::LoginError(anyhow!("NotFoundOnDB"))
-            } else {
-                e
-            }
-        })?;
-        
-        let mut logged_in = false;
+    /// Verifies credentials and opens a new session
+    /// * Returns whether the user already had a live session
+    pub async fn login(
+        pool: Data<DbPool>,
+        config: &SessionConfig,
+        username: String,
+        password: String,
+        device: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(UserDAO, SessionDAO, bool)> {
+        let user = match Self::by_username(pool.clone(), username).await {
+            Ok(user) => Some(user),
+            Err(Error::NotFoundOnDB) => None,
+            Err(e) => return Err(e),
+        };
 
-        // Check if sessionId exists
-        if let Some(_) = user.session_id {
-            logged_in = true;
+        // Unknown users and users without a password still pay for one Argon2 round,
+        // so response time doesn't tell whether the username exists
+        let password_hash = user.as_ref().and_then(|u| u.password_hash.clone());
+        let verified = block(move || match password_hash {
+            Some(password_hash) => verify_password(&password, &password_hash),
+            None => hash_password(&password).map(|_| false).unwrap_or(false),
+        })
+        .await?;
+
+        let user = match user {
+            Some(user) if verified => user,
+            _ => return Err(Error::InvalidCredentials),
+        };
+
+        SessionDAO::remove_expired(pool.clone(), user.id).await?;
+
+        // Check if another session is alive
+        let logged_in = !SessionDAO::by_user(pool.clone(), user.id).await?.is_empty();
+        if logged_in && !config.allow_concurrent {
+            return Err(Error::AlreadyLoggedIn(user.username));
         }
 
-        user.get_new_session(pool).await?;
-        Ok((user, logged_in))
+        let session = SessionDAO::create(pool, config, user.id, device, user_agent).await?;
+        Ok((user, session, logged_in))
     }
 
-    pub async fn get_new_session(&mut self, pool: Data<DbPool>) -> Result<String> {
-        self.update_session(pool, UserToken::new_session_id())
-            .await?;
-        Ok(self.session_id.clone().unwrap())
+    /// Changes the password after checking the old one
+    /// * Every other session of the user is revoked; `session_id` stays logged in
+    pub async fn change_password(
+        &mut self,
+        pool: Data<DbPool>,
+        session_id: String,
+        old_password: String,
+        new_password: String,
+    ) -> Result<()> {
+        check_password_strength(&new_password)?;
+
+        let password_hash = self.password_hash.clone();
+        let new_hash = block(move || match password_hash {
+            Some(password_hash) if verify_password(&old_password, &password_hash) => {
+                Some(hash_password(&new_password))
+            }
+            _ => None,
+        })
+        .await?
+        .ok_or(Error::InvalidCredentials)??;
+
+        self.store_password_hash(pool, new_hash, Some(session_id))
+            .await
     }
 
-    pub async fn logout(&mut self, pool: Data<DbPool>) -> Result<()> {
-        self.update_session(pool, String::new()).await
+    /// Sets a password without asking for the old one, e.g. the first password of an account
+    /// * Every session of the user is revoked
+    pub async fn set_password(&mut self, pool: Data<DbPool>, new_password: String) -> Result<()> {
+        check_password_strength(&new_password)?;
+
+        let new_hash = block(move || hash_password(&new_password)).await??;
+        self.store_password_hash(pool, new_hash, None).await
     }
 
-    pub async fn by_id(pool: Data<DbPool>, id: u64) -> Result<Self> {
+    /// Stores the hash and revokes every session of the user but `keep_session`, in one go
+    async fn store_password_hash(
+        &mut self,
+        pool: Data<DbPool>,
+        new_hash: String,
+        keep_session: Option<String>,
+    ) -> Result<()> {
         let mut conn = get_conn(pool).await;
+        let id = self.id;
+        let hash = new_hash.clone();
         block(move || {
-            dsl::users
-                .filter(dsl::id.eq(id))
-                .first::<UserDAO>(&mut conn)
+            conn.transaction(|conn| {
+                diesel::update(dsl::users.find(id))
+                    .set(dsl::password_hash.eq(Some(hash)))
+                    .execute(conn)?;
+                SessionDAO::revoke_others(conn, id, keep_session.as_deref())
+            })
         })
-        .await?
-        .map_err(Error::not_found_on_db)
+        .await??;
+
+        self.password_hash = Some(new_hash);
+        Ok(())
     }
 
-    pub async fn by_username<T>(pool: Data<DbPool>, username: T) -> Result<Self>
-    where
-        T: Into<String>,
-    {
-        let user_id: String = username.into();
+    pub fn allergy_codes(&self) -> Vec<u8> {
+        allergen::parse_codes(&self.allergies)
+    }
+
+    /// Replaces the allergies of the user; codes must be 1~18
+    pub async fn set_allergies(&mut self, pool: Data<DbPool>, codes: &[u8]) -> Result<()> {
+        let allergies = allergen::join_codes(&allergen::normalize(codes)?);
+
+        let mut conn = get_conn(pool).await;
+        let id = self.id;
+        let new_allergies = allergies.clone();
+        block(move || {
+            diesel::update(dsl::users.find(id))
+                .set(dsl::allergies.eq(new_allergies))
+                .execute(&mut conn)
+        })
+        .await??;
+
+        self.allergies = allergies;
+        Ok(())
+    }
+
+    /// Users who asked to be applied automatically
+    pub async fn with_auto_apply(pool: Data<DbPool>) -> Result<Vec<Self>> {
         let mut conn = get_conn(pool).await;
         block(move || {
             dsl::users
-                .filter(dsl::username.eq(user_id))
-                .first::<UserDAO>(&mut conn)
+                .filter(dsl::auto_apply.eq(true))
+                .load::<UserDAO>(&mut conn)
         })
         .await?
-        .map_err(Error::not_found_on_db)
+        .map_err(Into::into)
     }
 
-    pub async fn by_session_id<T>(pool: Data<DbPool>, session_id: T) -> Result<Self>
-    where
-        T: Into<String>,
-    {
-        let session_id: String = session_id.into();
+    pub async fn by_id(pool: Data<DbPool>, id: u64) -> Result<Self> {
         let mut conn = get_conn(pool).await;
         block(move || {
             dsl::users
-                .filter(dsl::session_id.eq(session_id))
+                .filter(dsl::id.eq(id))
                 .first::<UserDAO>(&mut conn)
         })
         .await?
-        .map_err(Error::no_such_session)
+        .map_err(Error::not_found_on_db)
     }
 
-    pub async fn update_session<T>(&mut self, pool: Data<DbPool>, session_id: T) -> Result<()>
+    pub async fn by_username<T>(pool: Data<DbPool>, username: T) -> Result<Self>
     where
         T: Into<String>,
     {
-        let session_id: String = session_id.into();
+        let user_id: String = username.into();
         let mut conn = get_conn(pool).await;
-
-        self.session_id = if session_id.is_empty() {
-            None
-        } else {
-            Some(session_id.clone())
-        };
-
-        let id = self.id;
-        let sid = self.session_id.clone();
         block(move || {
-            diesel::update(dsl::users.find(id))
-                .set(dsl::session_id.eq(&sid))
-                .execute(&mut conn)
+            dsl::users
+                .filter(dsl::username.eq(user_id))
+                .first::<UserDAO>(&mut conn)
         })
-        .await??;
-
-        Ok(())
+        .await?
+        .map_err(Error::not_found_on_db)
     }
 }
@@ -4,15 +4,96 @@ diesel::table! {
     applications (id) {
         id -> Unsigned<Bigint>,
         user_id -> Unsigned<Bigint>,
+        meal_date -> Date,
+        slot -> Unsigned<Tinyint>,
         created_at -> Datetime,
+        cancelled_at -> Nullable<Datetime>,
+        waitlisted -> Bool,
+        checked_in_at -> Nullable<Datetime>,
+        checked_in_by -> Nullable<Unsigned<Bigint>>,
     }
 }
 
 diesel::table! {
-    meals (id) {
+    auto_apply_runs (id) {
+        id -> Unsigned<Bigint>,
+        meal_date -> Date,
+        slot -> Unsigned<Tinyint>,
+        applied -> Unsigned<Integer>,
+        ran_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    billing_months (month) {
+        month -> Date,
+        closed_at -> Datetime,
+        closed_by -> Nullable<Unsigned<Bigint>>,
+    }
+}
+
+diesel::table! {
+    fee_charges (id) {
+        id -> Unsigned<Bigint>,
+        month -> Date,
+        user_id -> Unsigned<Bigint>,
+        meal_date -> Date,
+        slot -> Unsigned<Tinyint>,
+        price -> Unsigned<Integer>,
+        exempt -> Bool,
+    }
+}
+
+diesel::table! {
+    fee_exemptions (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        starts_on -> Date,
+        ends_on -> Nullable<Date>,
+        reason -> Varchar,
+        created_by -> Nullable<Unsigned<Bigint>>,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    meal_items (id) {
+        id -> Unsigned<Bigint>,
+        meal_id -> Unsigned<Bigint>,
+        name -> Varchar,
+        allergens -> Varchar,
+        sort_order -> Unsigned<Integer>,
+    }
+}
+
+diesel::table! {
+    meal_nutrients (id) {
         id -> Unsigned<Bigint>,
+        meal_id -> Unsigned<Bigint>,
         name -> Varchar,
+        unit -> Varchar,
+        amount -> Float,
+    }
+}
+
+diesel::table! {
+    meal_prices (id) {
+        id -> Unsigned<Bigint>,
+        starts_on -> Date,
+        slot -> Unsigned<Tinyint>,
+        price -> Unsigned<Integer>,
+        created_by -> Nullable<Unsigned<Bigint>>,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    meals (id) {
+        id -> Unsigned<Bigint>,
         date -> Date,
+        slot -> Unsigned<Tinyint>,
+        calories -> Nullable<Float>,
+        capacity -> Nullable<Unsigned<Integer>>,
     }
 }
 
@@ -20,9 +101,79 @@ diesel::table! {
     rates (id) {
         id -> Unsigned<Bigint>,
         user_id -> Unsigned<Bigint>,
+        meal_item_id -> Nullable<Unsigned<Bigint>>,
         food_name -> Varchar,
         rate_level -> Tinyint,
         created_at -> Datetime,
+        updated_at -> Nullable<Datetime>,
+        comment -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Varchar,
+        user_id -> Unsigned<Bigint>,
+        device -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        created_at -> Datetime,
+        last_seen_at -> Datetime,
+        expires_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    survey_answers (id) {
+        id -> Unsigned<Bigint>,
+        question_id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        meal_date -> Date,
+        slot -> Unsigned<Tinyint>,
+        meal_item_id -> Nullable<Unsigned<Bigint>>,
+        food_name -> Nullable<Varchar>,
+        number_value -> Nullable<Unsigned<Tinyint>>,
+        text_value -> Nullable<Text>,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    survey_questions (id) {
+        id -> Unsigned<Bigint>,
+        template_id -> Unsigned<Bigint>,
+        sort_order -> Unsigned<Integer>,
+        kind -> Unsigned<Tinyint>,
+        prompt -> Varchar,
+        per_dish -> Bool,
+        required -> Bool,
+        scale -> Nullable<Unsigned<Tinyint>>,
+        choices -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    survey_templates (id) {
+        id -> Unsigned<Bigint>,
+        name -> Varchar,
+        version -> Unsigned<Integer>,
+        slot -> Nullable<Unsigned<Tinyint>>,
+        active_from -> Date,
+        created_by -> Nullable<Unsigned<Bigint>>,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    suspensions (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        starts_on -> Date,
+        ends_on -> Date,
+        no_shows -> Unsigned<Integer>,
+        imposed_by -> Nullable<Unsigned<Bigint>>,
+        created_at -> Datetime,
+        lifted_at -> Nullable<Datetime>,
+        lifted_by -> Nullable<Unsigned<Bigint>>,
     }
 }
 
@@ -31,7 +182,10 @@ diesel::table! {
         id -> Unsigned<Bigint>,
         user_id -> Unsigned<Bigint>,
         rate_level -> Unsigned<Tinyint>,
+        rated_on -> Date,
         created_at -> Datetime,
+        updated_at -> Nullable<Datetime>,
+        comment -> Nullable<Varchar>,
     }
 }
 
@@ -40,17 +194,52 @@ diesel::table! {
         id -> Unsigned<Bigint>,
         username -> Varchar,
         name -> Varchar,
-        session_id -> Nullable<Varchar>,
+        grade -> Nullable<Unsigned<Tinyint>>,
+        class -> Nullable<Unsigned<Tinyint>>,
+        password_hash -> Nullable<Varchar>,
+        allergies -> Varchar,
         auto_apply -> Bool,
         is_teacher -> Bool,
         created_at -> Datetime,
     }
 }
 
+diesel::joinable!(applications -> users (user_id));
+diesel::joinable!(fee_charges -> billing_months (month));
+diesel::joinable!(fee_charges -> users (user_id));
+diesel::joinable!(fee_exemptions -> users (user_id));
+diesel::joinable!(meal_items -> meals (meal_id));
+diesel::joinable!(meal_nutrients -> meals (meal_id));
+diesel::joinable!(rates -> meal_items (meal_item_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(survey_answers -> meal_items (meal_item_id));
+diesel::joinable!(survey_answers -> survey_questions (question_id));
+diesel::joinable!(survey_answers -> users (user_id));
+diesel::joinable!(survey_questions -> survey_templates (template_id));
+diesel::joinable!(suspensions -> users (user_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     applications,
+    auto_apply_runs,
+    billing_months,
+    fee_charges,
+    fee_exemptions,
+    meal_items,
+    meal_nutrients,
+    meal_prices,
     meals,
     rates,
+    sessions,
+    survey_answers,
+    survey_questions,
+    survey_templates,
+    suspensions,
     total_rates,
     users,
 );
+
+diesel::allow_columns_to_appear_in_same_group_by_clause!(
+    users::grade,
+    users::class,
+    applications::slot,
+);
@@ -8,16 +8,17 @@ use std::fmt::Write;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     RustlsError(#[from] rustls::Error),
 
-    #[error("Config file is malformed. Check config.yml (source: {source:?})")]
-    ConfigError {
-        #[from]
-        source: serde_yaml::Error,
-    },
+    #[error("Certificate or private key is unusable: {0}")]
+    CertificateError(String),
+
+    #[error("Config is malformed. Check config.yml or UNHOMEAL_* variables ({})", .0.join("; "))]
+    ConfigError(Vec<String>),
 
     #[error("Error while executing blocking function")]
     BlockingError(#[from] BlockingError),
@@ -34,9 +35,30 @@ pub enum Error {
     #[error("Not found on the database")]
     NotFoundOnDB,
 
+    #[error("Invalid parameter: {0}")]
+    InvalidParam(String),
+
+    #[error("NEIS meal data is malformed: {0}")]
+    NeisError(String),
+
+    #[error("Failed to write the export: {0}")]
+    ExportError(String),
+
     #[error(transparent)]
     LoginError(#[from] anyhow::Error),
 
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+
+    #[error(
+        "Password must be at least {} characters long",
+        crate::password::MIN_PASSWORD_LENGTH
+    )]
+    WeakPassword,
+
+    #[error("Error while hashing password `{0}`")]
+    PasswordHashError(#[from] argon2::password_hash::Error),
+
     #[error(transparent)]
     TokenError(#[from] jsonwebtoken::errors::Error),
 
@@ -52,6 +74,30 @@ pub enum Error {
     #[error("Date has changed")]
     DateChanged,
 
+    #[error("Applications for this meal closed at {0}")]
+    DeadlinePassed(chrono::NaiveDateTime),
+
+    #[error("Check-in code is invalid: {0}")]
+    InvalidCheckInCode(String),
+
+    #[error("{0} has no seat for this meal")]
+    NotApplied(String),
+
+    #[error("Already checked in at {0}")]
+    AlreadyCheckedIn(chrono::NaiveDateTime),
+
+    #[error("Applications are suspended until {0}")]
+    Suspended(chrono::NaiveDate),
+
+    #[error("Billing for {0} is closed")]
+    MonthClosed(String),
+
+    #[error("Stored rate level {0} is unknown. Run `unhomeal_server repair-rates`")]
+    InvalidRateLevel(i8),
+
+    #[error("Comment contains words that are not allowed")]
+    InappropriateComment,
+
     #[error("Unprivileged request")]
     Unprivileged,
 
@@ -63,18 +109,33 @@ impl Error {
     pub fn tag_name(&self) -> String {
         String::from(match self {
             Error::RustlsError(_) => "RustlsError",
-            Error::ConfigError { source: _ } => "ConfigError",
+            Error::CertificateError(_) => "CertificateError",
+            Error::ConfigError(_) => "ConfigError",
             Error::BlockingError(_) => "BlockingError",
             Error::DBError(_) => "DBError",
             Error::DBConnectionError(_) => "DBConnectionError",
             Error::ActixWebError(_) => "ActixWebError",
             Error::NotFoundOnDB => "NotFoundOnDB",
+            Error::InvalidParam(_) => "InvalidParam",
+            Error::NeisError(_) => "NeisError",
+            Error::ExportError(_) => "ExportError",
             Error::LoginError(_) => "LoginError",
+            Error::InvalidCredentials => "InvalidCredentials",
+            Error::WeakPassword => "WeakPassword",
+            Error::PasswordHashError(_) => "PasswordHashError",
             Error::TokenError(_) => "TokenError",
             Error::TokenExpired => "TokenExpired",
             Error::AlreadyLoggedIn(_) => "AlreadyLoggedIn",
             Error::NoSuchSession => "NoSuchSession",
             Error::DateChanged => "DateChanged",
+            Error::DeadlinePassed(_) => "DeadlinePassed",
+            Error::InvalidCheckInCode(_) => "InvalidCheckInCode",
+            Error::NotApplied(_) => "NotApplied",
+            Error::AlreadyCheckedIn(_) => "AlreadyCheckedIn",
+            Error::Suspended(_) => "Suspended",
+            Error::MonthClosed(_) => "MonthClosed",
+            Error::InvalidRateLevel(_) => "InvalidRateLevel",
+            Error::InappropriateComment => "InappropriateComment",
             Error::Unprivileged => "Unprivileged",
             Error::IOError(_) => "IOError",
         })
@@ -92,13 +153,32 @@ impl Error {
     }
 }
 
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::ConfigError(vec![e.to_string()])
+    }
+}
+
 impl ResponseError for Error {
     fn status_code(&self) -> actix_web::http::StatusCode {
         use Error::*;
         match *self {
-            NotFoundOnDB | LoginError(_) | AlreadyLoggedIn(_) | NoSuchSession | Unprivileged => {
-                StatusCode::BAD_REQUEST
-            }
+            NotFoundOnDB
+            | LoginError(_)
+            | AlreadyLoggedIn(_)
+            | NoSuchSession
+            | Unprivileged
+            | WeakPassword
+            | InvalidParam(_)
+            | NeisError(_)
+            | DeadlinePassed(_)
+            | InvalidCheckInCode(_)
+            | NotApplied(_)
+            | AlreadyCheckedIn(_)
+            | Suspended(_)
+            | MonthClosed(_)
+            | InappropriateComment => StatusCode::BAD_REQUEST,
+            InvalidCredentials | TokenError(_) | TokenExpired => StatusCode::UNAUTHORIZED,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
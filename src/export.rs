@@ -0,0 +1,464 @@
+mod xlsx;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    body::{BodySize, MessageBody},
+    web::{block, Bytes, Data},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    billing::{self, BillingConfig},
+    db::{
+        models::{
+            application::{month_range, ApplicationDAO},
+            meal::MealSlot,
+            rate::RateDAO,
+            user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+/// Lets Excel open a CSV file as UTF-8 instead of the system code page (CP949 on Korean Windows)
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// Rows encoded into each chunk of a streamed CSV body
+const CSV_CHUNK_ROWS: usize = 500;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    /// Xlsx for a path ending with .xlsx, Csv otherwise
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".xlsx") {
+            Self::Xlsx
+        } else {
+            Self::Csv
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+}
+
+/// Leading characters that make Excel read a CSV field as a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+impl Cell {
+    /// Field of a CSV row; text that would run as a formula is kept as text with a leading `'`
+    fn to_text(&self) -> String {
+        match self {
+            Cell::Text(text) if text.starts_with(FORMULA_PREFIXES) => format!("'{}", text),
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_owned())
+    }
+}
+
+/// One table of an export, written as a CSV file or a single worksheet
+#[derive(Clone)]
+pub struct Sheet {
+    /// Worksheet name, also the stem of the file name
+    pub name: String,
+    pub headers: &'static [&'static str],
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        format!("{}.{}", self.name, format.extension())
+    }
+
+    /// Builds the XLSX file in memory, since a zip archive ends with an index of its entries
+    pub async fn into_xlsx(self) -> Result<Vec<u8>> {
+        block(move || xlsx::write(&self.name, self.headers, &self.rows))
+            .await?
+            .map_err(|e| Error::ExportError(e.to_string()))
+    }
+
+    /// CSV response body, encoded a chunk of rows at a time as the client reads it
+    pub fn csv_body(self, bom: bool) -> CsvBody {
+        CsvBody {
+            sheet: self,
+            bom,
+            sent: None,
+        }
+    }
+
+    /// Writes the sheet to a file, CSV unless the path ends with .xlsx
+    pub async fn save(self, path: &str, bom: bool) -> Result<()> {
+        match ExportFormat::from_path(path) {
+            ExportFormat::Csv => {
+                let file = BufWriter::new(File::create(path)?);
+                block(move || self.write_csv(file, bom))
+                    .await?
+                    .map_err(|e| Error::ExportError(e.to_string()))
+            }
+            ExportFormat::Xlsx => Ok(fs::write(path, self.into_xlsx().await?)?),
+        }
+    }
+
+    fn write_csv<W: io::Write>(&self, mut out: W, bom: bool) -> csv::Result<()> {
+        if bom {
+            out.write_all(UTF8_BOM)?;
+        }
+
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(self.headers)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(Cell::to_text))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Streamed CSV of a sheet; the first chunk carries the BOM and the header
+pub struct CsvBody {
+    sheet: Sheet,
+    bom: bool,
+    /// Rows sent so far, None before the first chunk
+    sent: Option<usize>,
+}
+
+impl CsvBody {
+    fn next_chunk(&mut self) -> csv::Result<Option<Bytes>> {
+        let start = match self.sent {
+            Some(sent) if sent >= self.sheet.rows.len() => return Ok(None),
+            Some(sent) => sent,
+            None => 0,
+        };
+        let end = (start + CSV_CHUNK_ROWS).min(self.sheet.rows.len());
+
+        let mut buf = Vec::new();
+        if self.sent.is_none() && self.bom {
+            buf.extend_from_slice(UTF8_BOM);
+        }
+        let mut writer = csv::Writer::from_writer(&mut buf);
+        if self.sent.is_none() {
+            writer.write_record(self.sheet.headers)?;
+        }
+        for row in &self.sheet.rows[start..end] {
+            writer.write_record(row.iter().map(Cell::to_text))?;
+        }
+        writer.flush()?;
+        drop(writer);
+        self.sent = Some(end);
+
+        Ok(Some(Bytes::from(buf)))
+    }
+}
+
+impl MessageBody for CsvBody {
+    type Error = csv::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Bytes, Self::Error>>> {
+        Poll::Ready(self.get_mut().next_chunk().transpose())
+    }
+}
+
+fn slot_name(slot: MealSlot) -> &'static str {
+    match slot {
+        MealSlot::Breakfast => "breakfast",
+        MealSlot::Lunch => "lunch",
+        MealSlot::Dinner => "dinner",
+    }
+}
+
+fn optional_number(n: Option<u8>) -> Cell {
+    n.map_or_else(|| Cell::from(""), |n| Cell::Number(n as f64))
+}
+
+/// Every application for meals of a "YYYY-MM" month, cancelled ones included
+pub async fn applications(pool: Data<DbPool>, month: &str) -> Result<Sheet> {
+    let (from, to) = month_range(month)?;
+
+    let rows = ApplicationDAO::with_users(pool, from, to, None)
+        .await?
+        .into_iter()
+        .map(|(application, user)| {
+            let status = if application.cancelled_at.is_some() {
+                "cancelled"
+            } else if application.waitlisted {
+                "waitlisted"
+            } else {
+                "applied"
+            };
+            vec![
+                user.username.into(),
+                user.name.into(),
+                optional_number(user.grade),
+                optional_number(user.class),
+                application.meal_date.to_string().into(),
+                slot_name(application.meal_slot()).into(),
+                status.into(),
+                application.created_at.to_string().into(),
+                application
+                    .cancelled_at
+                    .map(|at| at.to_string())
+                    .unwrap_or_default()
+                    .into(),
+            ]
+        })
+        .collect();
+
+    Ok(Sheet {
+        name: format!("applications-{}", from.format("%Y-%m")),
+        headers: &[
+            "username",
+            "name",
+            "grade",
+            "class",
+            "date",
+            "slot",
+            "status",
+            "applied_at",
+            "cancelled_at",
+        ],
+        rows,
+    })
+}
+
+/// Every dish rating given from `from` to `to`
+pub async fn rates(pool: Data<DbPool>, from: NaiveDate, to: NaiveDate) -> Result<Sheet> {
+    if from > to {
+        return Err(Error::InvalidParam(format!("{} is after {}", from, to)));
+    }
+
+    let rows = RateDAO::with_users(pool, from, to)
+        .await?
+        .into_iter()
+        .map(|(rate, user, slot)| rate_row(rate, user, slot))
+        .collect();
+
+    Ok(Sheet {
+        name: format!("rates-{}-{}", from, to),
+        headers: &[
+            "username",
+            "name",
+            "grade",
+            "class",
+            "date",
+            "slot",
+            "status",
+            "dish",
+            "rate_level",
+            "rated_at",
+            "comment",
+        ],
+        rows,
+    })
+}
+
+/// Row of the rates export; the student's comment goes through the same escaping as any text
+fn rate_row(rate: RateDAO, user: UserDAO, slot: Option<u8>) -> Vec<Cell> {
+    // Dishes withdrawn from the menu since are still exported, without a slot
+    let status = if rate.meal_item_id.is_some() {
+        "rated"
+    } else {
+        "withdrawn"
+    };
+    vec![
+        user.username.into(),
+        user.name.into(),
+        optional_number(user.grade),
+        optional_number(user.class),
+        rate.created_at.date().to_string().into(),
+        slot.and_then(MealSlot::from_code)
+            .map_or("", slot_name)
+            .into(),
+        status.into(),
+        rate.food_name.into(),
+        Cell::Number(rate.rate_level as f64),
+        rate.created_at.to_string().into(),
+        rate.comment.unwrap_or_default().into(),
+    ]
+}
+
+/// Charges of a month per student, frozen if the month is closed
+pub async fn billing(pool: Data<DbPool>, config: &BillingConfig, month: &str) -> Result<Sheet> {
+    let billing = billing::month(pool, config, month).await?;
+    let status = if billing.closed_at.is_some() {
+        "closed"
+    } else {
+        "open"
+    };
+
+    let rows = billing
+        .statements
+        .into_iter()
+        .map(|statement| {
+            vec![
+                statement.username.into(),
+                statement.name.into(),
+                optional_number(statement.grade),
+                optional_number(statement.class),
+                Cell::Number(statement.meals as f64),
+                Cell::Number(statement.exempt_meals as f64),
+                Cell::Number(statement.amount as f64),
+                status.into(),
+            ]
+        })
+        .collect();
+
+    Ok(Sheet {
+        name: format!("billing-{}", billing.month.format("%Y-%m")),
+        headers: &[
+            "username",
+            "name",
+            "grade",
+            "class",
+            "meals",
+            "exempt_meals",
+            "amount",
+            "status",
+        ],
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(rows: usize) -> Sheet {
+        Sheet {
+            name: String::from("rates"),
+            headers: &["username", "dish"],
+            rows: (0..rows)
+                .map(|i| vec![Cell::from(format!("s{}", i)), Cell::from("김치, 볶음밥")])
+                .collect(),
+        }
+    }
+
+    fn streamed(sheet: Sheet, bom: bool) -> (Vec<u8>, usize) {
+        let mut body = sheet.csv_body(bom);
+        let mut csv = Vec::new();
+        let mut chunks = 0;
+        while let Some(chunk) = body.next_chunk().unwrap() {
+            csv.extend_from_slice(&chunk);
+            chunks += 1;
+        }
+        (csv, chunks)
+    }
+
+    #[test]
+    fn streams_the_same_csv_it_saves() {
+        for rows in [0, 1, CSV_CHUNK_ROWS, CSV_CHUNK_ROWS * 2 + 1] {
+            let mut saved = Vec::new();
+            sheet(rows).write_csv(&mut saved, true).unwrap();
+
+            let (csv, chunks) = streamed(sheet(rows), true);
+            assert_eq!(csv, saved);
+            assert_eq!(chunks, rows.div_ceil(CSV_CHUNK_ROWS).max(1));
+        }
+    }
+
+    #[test]
+    fn keeps_formulas_as_text() {
+        for formula in ["=1+1", "+82 10", "-2+3", "@SUM(A1)", "\tx", "\rx"] {
+            assert_eq!(Cell::from(formula).to_text(), format!("'{}", formula));
+        }
+        assert_eq!(Cell::from("김=치").to_text(), "김=치");
+        assert_eq!(Cell::Number(-1.0).to_text(), "-1");
+    }
+
+    #[test]
+    fn escapes_comments_in_the_rates_export() {
+        let created_at = NaiveDate::from_ymd(2026, 10, 18).and_hms(12, 30, 0);
+        let rate = RateDAO {
+            id: 1,
+            user_id: 1,
+            meal_item_id: Some(1),
+            food_name: String::from("김치볶음밥"),
+            rate_level: 2,
+            created_at,
+            updated_at: None,
+            comment: Some(String::from(
+                "=HYPERLINK(\"http://example.com\",\"맛있어요\")",
+            )),
+        };
+        let user = UserDAO {
+            id: 1,
+            username: String::from("s1234"),
+            name: String::from("김급식"),
+            grade: Some(1),
+            class: Some(2),
+            password_hash: None,
+            allergies: String::new(),
+            auto_apply: false,
+            is_teacher: false,
+            created_at,
+        };
+
+        let row = rate_row(rate, user, Some(MealSlot::Lunch.code()));
+        assert_eq!(
+            row.last().unwrap().to_text(),
+            "'=HYPERLINK(\"http://example.com\",\"맛있어요\")"
+        );
+        assert_eq!(row[5].to_text(), "lunch");
+    }
+
+    #[test]
+    fn starts_with_a_bom_only_when_asked() {
+        let (csv, _) = streamed(sheet(1), true);
+        assert!(csv.starts_with(UTF8_BOM));
+        assert_eq!(
+            &csv[UTF8_BOM.len()..],
+            "username,dish\ns0,\"김치, 볶음밥\"\n".as_bytes()
+        );
+
+        let (csv, _) = streamed(sheet(1), false);
+        assert!(csv.starts_with(b"username,dish\n"));
+    }
+}
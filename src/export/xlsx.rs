@@ -0,0 +1,250 @@
+//! Just enough of XLSX for one worksheet of text and numbers with a bold header row
+
+use std::io::{Cursor, Write};
+
+use zip::{result::ZipResult, write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::Cell;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// Style 0 is the default, style 1 is bold
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+/// Workbook with a single worksheet; the first row is the bold header
+/// * The worksheet is written row by row into its compressed zip entry
+/// * Text is always an inline string, so text like `=1+1` is never read as a formula
+pub fn write(sheet_name: &str, headers: &[&str], rows: &[Vec<Cell>]) -> ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, part) in [
+        ("[Content_Types].xml", CONTENT_TYPES),
+        ("_rels/.rels", ROOT_RELS),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+        ("xl/styles.xml", STYLES),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(part.as_bytes())?;
+    }
+
+    zip.start_file("xl/workbook.xml", options)?;
+    write!(
+        zip,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        escape(&valid_sheet_name(sheet_name))
+    )?;
+
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    )?;
+    let header = headers
+        .iter()
+        .map(|header| Cell::Text(header.to_string()))
+        .collect::<Vec<_>>();
+    for (i, cells) in std::iter::once(&header).chain(rows).enumerate() {
+        let row = i + 1;
+        let style = if i == 0 { r#" s="1""# } else { "" };
+
+        write!(zip, r#"<row r="{}">"#, row)?;
+        for (col, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", column_name(col), row);
+            match cell {
+                Cell::Text(text) => write!(
+                    zip,
+                    r#"<c r="{}" t="inlineStr"{}><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    style,
+                    escape(text)
+                )?,
+                Cell::Number(number) => write!(
+                    zip,
+                    r#"<c r="{}"{}><v>{}</v></c>"#,
+                    reference, style, number
+                )?,
+            }
+        }
+        zip.write_all(b"</row>")?;
+    }
+    zip.write_all(b"</sheetData></worksheet>")?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// 0 => A, 25 => Z, 26 => AA
+fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// Excel refuses names over 31 characters or with any of []:*?/\
+fn valid_sheet_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect()
+}
+
+/// Escapes markup and drops control characters XML can't carry
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    /// Text of every cell of the worksheet, row by row
+    fn read_cells(xlsx: Vec<u8>) -> Vec<Vec<String>> {
+        let mut archive = ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        for part in [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "xl/workbook.xml",
+            "xl/_rels/workbook.xml.rels",
+            "xl/styles.xml",
+        ] {
+            let mut xml = String::new();
+            archive
+                .by_name(part)
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+            roxmltree::Document::parse(&xml).unwrap();
+        }
+
+        let mut xml = String::new();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        doc.descendants()
+            .filter(|node| node.has_tag_name("row"))
+            .map(|row| {
+                row.children()
+                    .filter(|cell| cell.has_tag_name("c"))
+                    .map(|cell| {
+                        cell.descendants()
+                            .filter(|node| node.has_tag_name("t") || node.has_tag_name("v"))
+                            .filter_map(|node| node.text())
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn names_columns_like_excel() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(51), "AZ");
+        assert_eq!(column_name(52), "BA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn round_trips_through_a_zip_reader() {
+        let rows = vec![
+            vec![Cell::from("s1234"), Cell::from("김급식"), Cell::Number(3.0)],
+            vec![
+                Cell::from("<a & b>"),
+                Cell::from("\"따옴표\""),
+                Cell::Number(-1.5),
+            ],
+        ];
+        let xlsx = write(
+            "rates-2026-10-01-2026-10-18",
+            &["username", "name", "n"],
+            &rows,
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_cells(xlsx),
+            vec![
+                vec!["username", "name", "n"],
+                vec!["s1234", "김급식", "3"],
+                vec!["<a & b>", "\"따옴표\"", "-1.5"],
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_formulas_as_inline_strings() {
+        let xlsx = write(
+            "formula",
+            &["dish"],
+            &[vec![Cell::from("=HYPERLINK(\"x\")")]],
+        )
+        .unwrap();
+
+        let mut xml = String::new();
+        ZipArchive::new(Cursor::new(xlsx.clone()))
+            .unwrap()
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(!xml.contains("<f>"));
+        assert!(xml.contains(r#"<c r="A2" t="inlineStr">"#));
+        assert_eq!(read_cells(xlsx)[1], vec!["=HYPERLINK(\"x\")"]);
+    }
+
+    #[test]
+    fn writes_an_empty_sheet() {
+        let xlsx = write("empty", &["username"], &[]).unwrap();
+
+        assert_eq!(read_cells(xlsx), vec![vec!["username"]]);
+    }
+
+    #[test]
+    fn drops_control_characters() {
+        assert_eq!(escape("a\u{1}b\tc"), "ab\tc");
+    }
+
+    #[test]
+    fn shortens_sheet_names_for_excel() {
+        assert_eq!(valid_sheet_name("a/b:c"), "abc");
+        assert_eq!(valid_sheet_name(&"가".repeat(40)).chars().count(), 31);
+    }
+}
@@ -1,7 +1,21 @@
+mod allergen;
+mod auth;
+mod auto_apply;
+mod billing;
+mod cli;
+mod clock;
+mod comment;
+mod config;
 mod db;
 mod error;
+mod export;
 pub use error::Result;
+mod neis;
+mod password;
+mod penalty;
 mod routes;
+mod survey;
+mod tls;
 mod token;
 
 use actix_web::{
@@ -12,96 +26,137 @@ use actix_web::{
 use diesel::r2d2::Pool;
 use diesel::{r2d2::ConnectionManager, MysqlConnection};
 use routes::get_applications_route;
-use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::fs::File;
+use std::sync::Arc;
 
 use crate::{
+    cli::Command,
+    comment::CommentFilter,
+    config::Config,
     db::DbPool,
     routes::{
-        apply_route, get_rates_route, get_user_rate_route, login_route, logout_route,
-        post_rate_route, rank_route, test_route,
+        add_exemption_route, allergens_route, allergy_report_route, apply_route,
+        billing_summary_route, change_password_route, check_in_code_route, check_in_route,
+        close_billing_route, create_survey_template_route, delete_meal_route, end_exemption_route,
+        exemptions_route, export_applications_route, export_billing_route, export_rates_route,
+        get_meal_range_route, get_meal_route, get_rates_route, get_user_rate_route,
+        has_applied_route, headcount_route, import_meal_route, login_route, logout_route,
+        meal_capacity_route, meal_prices_route, no_show_route, post_meal_route, post_rate_route,
+        rank_route, revoke_session_route, roster_route, sessions_route, set_allergies_route,
+        set_meal_price_route, statement_route, survey_route, survey_templates_route,
+        suspension_route, suspensions_route, test_route, user_route,
     },
+    tls::{redirect_to_https, reload_on_sighup, CertResolver},
+    token::TokenKey,
 };
 
-/*
-use std::io::BufReader;
-use rustls::{Certificate, PrivateKey, ServerConfig};
-use rustls_pemfile::{read_one, Item};
-*/
-
-#[derive(Debug, Deserialize, Serialize)]
-struct Config {
-    port: u16,
-    cert_location: String,
-    key_location: String,
-    database_url: String,
-    token_generation_key: String,
-}
-
-fn load_config() -> Result<Config> {
-    let config_file = File::open("./config.yml")?;
-    let config: Config = serde_yaml::from_reader(config_file)?;
-
-    // TODO: Verify if config is not malformed (e.g. cert_location or key_location is empty)
-
-    Ok(config)
-}
-
 #[actix_web::main]
 async fn main() -> Result<()> {
-    let config = load_config()?;
-
-    /*
-    // generating certs for localhost :
-    //      openssl req -x509 -newkey rsa:4096 -nodes -keyout key.pem -out cert.pem -days 365 -subj '/CN=localhost'
-    print!("Reading certificate: ");
-    let cert_file = File::open(config.cert_location)?;
-    let key_file = File::open(config.key_location)?;
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let command = Command::from_args()?;
+    let config = Config::load()?;
+    clock::set_utc_offset(config.utc_offset());
 
-    let mut cert_reader = BufReader::new(cert_file);
-    let mut key_reader = BufReader::new(key_file);
-
-    let cert_buf = match read_one(&mut cert_reader)?.unwrap() {
-        Item::X509Certificate(der) => der,
-        _ => {
-            panic!("Invalid certificate")
-        }
-    };
-
-    let key_buf = match read_one(&mut key_reader)?.unwrap() {
-        Item::PKCS8Key(der) => der,
-        _ => {
-            panic!("Invalid private key")
-        }
-    };
-
-    let cert_chain = vec![Certificate(cert_buf)];
-    let key = PrivateKey(key_buf);
-    println!("done");
-
-    let rustls_config = ServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth()
-        .with_single_cert(cert_chain, key)?;
-    */
     let connection = ConnectionManager::<MysqlConnection>::new(&config.database_url);
     let pool = Pool::builder()
+        .max_size(config.pool_size)
         .build(connection)
         .expect("Error creating dbpool");
 
-    HttpServer::new(move || {
+    match command {
+        Command::Serve => serve(config, pool).await,
+        Command::ImportMeals { path } => cli::import_meals(Data::new(pool), &path).await,
+        Command::ExportApplications { month, path, bom } => {
+            cli::export_applications(Data::new(pool), &month, &path, bom).await
+        }
+        Command::ExportRates {
+            from,
+            to,
+            path,
+            bom,
+        } => cli::export_rates(Data::new(pool), from, to, &path, bom).await,
+        Command::ExportBilling { month, path, bom } => {
+            cli::export_billing(Data::new(pool), &config.billing, &month, &path, bom).await
+        }
+        Command::RepairRates { dry_run } => cli::repair_rates(Data::new(pool), dry_run).await,
+        Command::SetPassword { username } => cli::set_password(Data::new(pool), &username).await,
+    }
+}
+
+async fn serve(config: Config, pool: DbPool) -> Result<()> {
+    let token_key = Data::new(TokenKey::new(&config.token_generation_key));
+    let session_config = Data::new(config.session.clone());
+    let apply_config = Data::new(config.apply.clone());
+    let check_in_config = Data::new(config.check_in.clone());
+    let penalty_config = Data::new(config.penalty.clone());
+    let billing_config = Data::new(config.billing.clone());
+    let comment_filter = Data::new(CommentFilter::new(&config.comments)?);
+
+    auto_apply::spawn(
+        Data::new(pool.clone()),
+        config.auto_apply.clone(),
+        config.apply.clone(),
+        config.penalty.clone(),
+    );
+
+    let server = HttpServer::new(move || {
         App::new()
             .service(test_route) //서버 온라인 체크
             .service(login_route) // 로그인
             .service(logout_route) // 로그아웃
+            .service(change_password_route) // 비밀번호 변경
+            .service(sessions_route) // 로그인 기기 목록
+            .service(revoke_session_route) // 기기 로그아웃
+            .service(user_route) // 사용자 정보
+            .service(set_allergies_route) // 알레르기 등록
+            .service(allergens_route) // 알레르기 목록
+            .service(get_meal_route) // 식단
+            .service(get_meal_range_route) // 기간별 식단
+            .service(post_meal_route) // 식단 등록
+            .service(delete_meal_route) // 식단 삭제
+            .service(
+                web::resource("/meals/import")
+                    .app_data(web::PayloadConfig::new(neis::MAX_EXPORT_BYTES))
+                    .route(web::post().to(import_meal_route)),
+            ) // NEIS 식단 가져오기
+            .service(meal_capacity_route) // 식사 정원
             .service(apply_route) // 신청
+            .service(has_applied_route) // 신청 여부
             .service(get_applications_route) // 신청 명단
+            .service(roster_route) // 반별 신청 명단
+            .service(headcount_route) // 신청 인원
+            .service(check_in_code_route) // 체크인 QR 코드
+            .service(check_in_route) // 체크인
+            .service(no_show_route) // 미출석 통계
+            .service(suspension_route) // 신청 정지 설정
+            .service(suspensions_route) // 신청 정지 목록
+            .service(export_applications_route) // 신청 내역 내보내기
+            .service(export_rates_route) // 평가 내보내기
+            .service(export_billing_route) // 급식비 내보내기
+            .service(statement_route) // 급식비 명세서
+            .service(billing_summary_route) // 급식비 집계
+            .service(close_billing_route) // 급식비 마감
+            .service(meal_prices_route) // 급식 단가 목록
+            .service(set_meal_price_route) // 급식 단가 설정
+            .service(exemptions_route) // 급식비 면제 목록
+            .service(add_exemption_route) // 급식비 면제 등록
+            .service(end_exemption_route) // 급식비 면제 종료
+            .service(allergy_report_route) // 알레르기 대상 학생
             .service(post_rate_route) // 설문 제출
             .service(get_rates_route) // 학생 설문 정보
             .service(get_user_rate_route) // 단일 학생 설문 정보
+            .service(survey_route) // 설문 문항
+            .service(survey_templates_route) // 설문 템플릿 목록
+            .service(create_survey_template_route) // 설문 템플릿 등록
             .service(rank_route) // 급식 랭킹
             .app_data(Data::new(pool.clone()))
+            .app_data(token_key.clone())
+            .app_data(session_config.clone())
+            .app_data(apply_config.clone())
+            .app_data(check_in_config.clone())
+            .app_data(penalty_config.clone())
+            .app_data(billing_config.clone())
+            .app_data(comment_filter.clone())
             .app_data(web::JsonConfig::default().error_handler(|err, _req| {
                 actix_web::error::InternalError::from_response(
                     "",
@@ -117,14 +172,67 @@ async fn main() -> Result<()> {
                 )
                 .into()
             }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _req| {
+                actix_web::error::InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("application/json")
+                        .json(json!({
+                            "is_error": true,
+                            "error": {
+                                "type": "QueryError",
+                                "content": format!("{}", err)
+                            }
+                        })),
+                )
+                .into()
+            }))
             .wrap(Logger::default())
             .wrap(Logger::new("%a %{User-Agent}i"))
-    })
-    //.bind_rustls(("127.0.0.1", config.port), rustls_config)?
-    .bind(("127.0.0.1", config.port))?
-    .workers(8)
-    .run()
-    .await?;
+    });
+
+    if !config.use_tls {
+        server
+            .bind((config.host.as_str(), config.port))?
+            .workers(config.workers)
+            .run()
+            .await?;
+
+        return Ok(());
+    }
+
+    let resolver = Arc::new(CertResolver::new(
+        config.cert_location.as_str(),
+        config.key_location.as_str(),
+    )?);
+    log::info!("Read certificate {}", config.cert_location);
+    reload_on_sighup(resolver.clone())?;
+
+    let https_server = server
+        .bind_rustls(
+            (config.host.as_str(), config.port),
+            resolver.server_config(),
+        )?
+        .workers(config.workers)
+        .run();
+
+    if let Some(http_port) = config.http_redirect_port {
+        let https_port = config.port;
+        let redirect_server = HttpServer::new(move || {
+            App::new()
+                .default_service(web::to(move |req| async move {
+                    redirect_to_https(req, https_port)
+                }))
+                .wrap(Logger::default())
+        })
+        .bind((config.host.as_str(), http_port))?
+        .workers(1)
+        .run();
+
+        tokio::try_join!(https_server, redirect_server)?;
+    } else {
+        https_server.await?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+
+use actix_web::web::Data;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    allergen::Allergen,
+    db::{
+        models::meal::{MealDAO, MealSlot},
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+/// Largest export accepted by /meals/import; a year of meals is well below this
+pub const MAX_EXPORT_BYTES: usize = 4 * 1024 * 1024;
+
+/// RESULT.CODE of a successful answer, and of an answer without any row
+const CODE_OK: &str = "INFO-000";
+const CODE_NO_DATA: &str = "INFO-200";
+
+/// One row of mealServiceDietInfo, as exported from NEIS
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct NeisRow {
+    mlsv_ymd: String,
+    mmeal_sc_code: String,
+    ddish_nm: String,
+    #[serde(default)]
+    cal_info: Option<String>,
+    #[serde(default)]
+    ntr_info: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct NeisResult {
+    code: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct JsonSection {
+    #[serde(default)]
+    head: Vec<serde_json::Value>,
+    #[serde(default)]
+    row: Vec<NeisRow>,
+}
+
+#[derive(Deserialize)]
+struct JsonRoot {
+    #[serde(rename = "mealServiceDietInfo", default)]
+    sections: Vec<JsonSection>,
+    #[serde(rename = "RESULT")]
+    result: Option<NeisResult>,
+}
+
+#[derive(Clone)]
+pub struct NeisDish {
+    pub name: String,
+    /// Allergen codes (1~18) written after the dish name, sorted
+    pub allergens: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct NeisNutrient {
+    pub name: String,
+    pub unit: String,
+    pub amount: f32,
+}
+
+#[derive(Clone)]
+pub struct NeisMeal {
+    pub date: NaiveDate,
+    pub slot: MealSlot,
+    pub dishes: Vec<NeisDish>,
+    pub calories: Option<f32>,
+    pub nutrients: Vec<NeisNutrient>,
+}
+
+impl NeisMeal {
+    fn from_row(row: NeisRow) -> Result<Self> {
+        let date = NaiveDate::parse_from_str(row.mlsv_ymd.trim(), "%Y%m%d").map_err(|_| {
+            Error::NeisError(format!(
+                "MLSV_YMD `{}` is not a YYYYMMDD date",
+                row.mlsv_ymd
+            ))
+        })?;
+
+        let slot = row
+            .mmeal_sc_code
+            .trim()
+            .parse()
+            .ok()
+            .and_then(MealSlot::from_code)
+            .ok_or_else(|| {
+                Error::NeisError(format!(
+                    "MMEAL_SC_CODE `{}` is not a meal code",
+                    row.mmeal_sc_code
+                ))
+            })?;
+
+        let mut dishes: Vec<NeisDish> = Vec::new();
+        for dish in split_lines(&row.ddish_nm).filter_map(parse_dish) {
+            // The same dish can be listed twice; keep one with every allergen
+            match dishes.iter_mut().find(|d| d.name == dish.name) {
+                Some(same) => {
+                    same.allergens.extend(dish.allergens);
+                    same.allergens.sort_unstable();
+                    same.allergens.dedup();
+                }
+                None => dishes.push(dish),
+            }
+        }
+        if dishes.is_empty() {
+            return Err(Error::NeisError(format!("Meal of {} has no dishes", date)));
+        }
+
+        let calories = row.cal_info.as_deref().and_then(parse_calories);
+        let nutrients = row
+            .ntr_info
+            .as_deref()
+            .map(|info| split_lines(info).filter_map(parse_nutrient).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            date,
+            slot,
+            dishes,
+            calories,
+            nutrients,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ImportSummary {
+    /// Meals whose menu was written
+    pub imported: usize,
+}
+
+/// Imports every meal of a NEIS export
+pub async fn import(pool: Data<DbPool>, src: &str) -> Result<ImportSummary> {
+    let imported = MealDAO::import(pool, parse(src)?).await?;
+
+    Ok(ImportSummary { imported })
+}
+
+/// Parses a NEIS export, telling JSON from XML by its first character
+pub fn parse(src: &str) -> Result<Vec<NeisMeal>> {
+    let src = src.trim_start_matches('\u{feff}').trim_start();
+    let rows = if src.starts_with('<') {
+        rows_from_xml(src)?
+    } else {
+        rows_from_json(src)?
+    };
+
+    rows.into_iter().map(NeisMeal::from_row).collect()
+}
+
+fn check_result(result: NeisResult) -> Result<bool> {
+    match result.code.as_str() {
+        CODE_OK => Ok(true),
+        CODE_NO_DATA => Ok(false),
+        _ => Err(Error::NeisError(format!(
+            "{} {}",
+            result.code, result.message
+        ))),
+    }
+}
+
+fn rows_from_json(src: &str) -> Result<Vec<NeisRow>> {
+    let root: JsonRoot = serde_json::from_str(src).map_err(|e| Error::NeisError(e.to_string()))?;
+
+    if let Some(result) = root.result {
+        if !check_result(result)? {
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut rows = Vec::new();
+    for section in root.sections {
+        for head in section.head {
+            if let Some(result) = head.get("RESULT") {
+                let result =
+                    NeisResult::deserialize(result).map_err(|e| Error::NeisError(e.to_string()))?;
+                check_result(result)?;
+            }
+        }
+        rows.extend(section.row);
+    }
+    Ok(rows)
+}
+
+fn rows_from_xml(src: &str) -> Result<Vec<NeisRow>> {
+    let doc = roxmltree::Document::parse(src).map_err(|e| Error::NeisError(e.to_string()))?;
+
+    for result in doc.descendants().filter(|n| n.has_tag_name("RESULT")) {
+        let field = |name: &str| {
+            result
+                .children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .unwrap_or_default()
+                .trim()
+                .to_owned()
+        };
+        let result = NeisResult {
+            code: field("CODE"),
+            message: field("MESSAGE"),
+        };
+        if !check_result(result)? {
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut rows = Vec::new();
+    for row in doc.descendants().filter(|n| n.has_tag_name("row")) {
+        let fields = row
+            .children()
+            .filter(|n| n.is_element())
+            .map(|n| (n.tag_name().name(), n.text().unwrap_or_default()))
+            .collect::<HashMap<_, _>>();
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .map(|s| s.to_string())
+                .ok_or_else(|| Error::NeisError(format!("A row has no {}", name)))
+        };
+
+        rows.push(NeisRow {
+            mlsv_ymd: field("MLSV_YMD")?,
+            mmeal_sc_code: field("MMEAL_SC_CODE")?,
+            ddish_nm: field("DDISH_NM")?,
+            cal_info: field("CAL_INFO").ok(),
+            ntr_info: field("NTR_INFO").ok(),
+        });
+    }
+    Ok(rows)
+}
+
+/// NEIS separates dishes and nutrients with `<br/>`
+fn split_lines(s: &str) -> impl Iterator<Item = &str> {
+    s.split("<br/>")
+        .flat_map(|line| line.split("<br>"))
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+}
+
+/// "김치찌개(5.9.13)" or the older "김치찌개5.9.13." into the name and allergen codes
+fn parse_dish(line: &str) -> Option<NeisDish> {
+    let is_code = |c: char| c.is_ascii_digit() || c == '.' || c == ' ';
+
+    let (name, codes) = match line.strip_suffix(')').and_then(|s| s.rsplit_once('(')) {
+        Some((name, codes)) if !codes.is_empty() && codes.chars().all(is_code) => (name, codes),
+        _ => {
+            let name = line.trim_end_matches(is_code);
+            let codes = &line[name.len()..];
+            if codes.contains('.') {
+                (name, codes)
+            } else {
+                (line, "")
+            }
+        }
+    };
+
+    let name = name.trim().trim_end_matches(['*', '@', '#']).trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut allergens = codes
+        .split('.')
+        .filter_map(|code| code.trim().parse::<u8>().ok())
+        .filter(|code| Allergen::by_code(*code).is_some())
+        .collect::<Vec<_>>();
+    allergens.sort_unstable();
+    allergens.dedup();
+
+    Some(NeisDish {
+        name: name.to_owned(),
+        allergens,
+    })
+}
+
+/// "650.3 Kcal"
+fn parse_calories(info: &str) -> Option<f32> {
+    info.split_whitespace().next()?.parse().ok()
+}
+
+/// "탄수화물(g) : 90.1"
+fn parse_nutrient(line: &str) -> Option<NeisNutrient> {
+    let (label, amount) = line.split_once(':')?;
+    let amount = amount.trim().parse::<f32>().ok()?;
+    let (name, unit) = match label
+        .trim()
+        .strip_suffix(')')
+        .and_then(|s| s.split_once('('))
+    {
+        Some((name, unit)) => (name.trim(), unit.trim()),
+        None => (label.trim(), ""),
+    };
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(NeisNutrient {
+        name: name.to_owned(),
+        unit: unit.to_owned(),
+        amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dish(line: &str) -> Option<(String, Vec<u8>)> {
+        parse_dish(line).map(|dish| (dish.name, dish.allergens))
+    }
+
+    #[test]
+    fn parses_dishes_with_allergens_in_parentheses() {
+        assert_eq!(
+            dish("김치찌개(5.9.13)"),
+            Some((String::from("김치찌개"), vec![5, 9, 13]))
+        );
+        assert_eq!(
+            dish("돈까스 (13.1.5.1)"),
+            Some((String::from("돈까스"), vec![1, 5, 13]))
+        );
+        assert_eq!(dish("쌀밥"), Some((String::from("쌀밥"), vec![])));
+    }
+
+    #[test]
+    fn parses_dishes_with_trailing_allergens() {
+        assert_eq!(
+            dish("김치찌개5.9.13."),
+            Some((String::from("김치찌개"), vec![5, 9, 13]))
+        );
+        assert_eq!(dish("우유*2."), Some((String::from("우유"), vec![2])));
+    }
+
+    #[test]
+    fn keeps_digits_that_belong_to_the_name() {
+        assert_eq!(dish("비타500"), Some((String::from("비타500"), vec![])));
+        assert_eq!(dish("떡(국산)"), Some((String::from("떡(국산)"), vec![])));
+    }
+
+    #[test]
+    fn drops_unknown_allergens_and_empty_dishes() {
+        assert_eq!(
+            dish("라면(5.6.19.0)"),
+            Some((String::from("라면"), vec![5, 6]))
+        );
+        assert_eq!(dish("(1.2)"), None);
+        assert_eq!(dish("*#"), None);
+    }
+
+    #[test]
+    fn parses_nutrients() {
+        let nutrient = parse_nutrient("탄수화물(g) : 90.1").unwrap();
+        assert_eq!(
+            (
+                nutrient.name.as_str(),
+                nutrient.unit.as_str(),
+                nutrient.amount
+            ),
+            ("탄수화물", "g", 90.1)
+        );
+
+        let nutrient = parse_nutrient("비타민A(R.E) : 120.5").unwrap();
+        assert_eq!(
+            (nutrient.name.as_str(), nutrient.unit.as_str()),
+            ("비타민A", "R.E")
+        );
+
+        let nutrient = parse_nutrient("열량 : 650").unwrap();
+        assert_eq!(
+            (nutrient.name.as_str(), nutrient.unit.as_str()),
+            ("열량", "")
+        );
+
+        assert!(parse_nutrient("단백질(g) : -").is_none());
+        assert!(parse_nutrient("단백질(g) 30").is_none());
+        assert!(parse_nutrient("(g) : 30").is_none());
+    }
+
+    #[test]
+    fn parses_calories() {
+        assert_eq!(parse_calories("650.3 Kcal"), Some(650.3));
+        assert_eq!(parse_calories("Kcal"), None);
+    }
+
+    #[test]
+    fn parses_json_exports() {
+        let src = r#"{"mealServiceDietInfo": [
+            {"head": [{"list_total_count": 1}, {"RESULT": {"CODE": "INFO-000", "MESSAGE": "정상 처리되었습니다."}}]},
+            {"row": [{
+                "MLSV_YMD": "20261019",
+                "MMEAL_SC_CODE": "2",
+                "DDISH_NM": "쌀밥<br/>김치찌개(5.9)<br/>김치찌개(13)<br/>우유(2)",
+                "CAL_INFO": "650.3 Kcal",
+                "NTR_INFO": "탄수화물(g) : 90.1<br/>단백질(g) : 30.2"
+            }]}
+        ]}"#;
+
+        let meals = parse(src).unwrap();
+        assert_eq!(meals.len(), 1);
+        let meal = &meals[0];
+        assert_eq!(meal.date, NaiveDate::from_ymd(2026, 10, 19));
+        assert_eq!(meal.slot, MealSlot::Lunch);
+        assert_eq!(meal.calories, Some(650.3));
+        assert_eq!(meal.nutrients.len(), 2);
+        assert_eq!(
+            meal.dishes
+                .iter()
+                .map(|dish| (dish.name.as_str(), dish.allergens.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("쌀밥", vec![]),
+                ("김치찌개", vec![5, 9, 13]),
+                ("우유", vec![2])
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_xml_exports() {
+        let src = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<mealServiceDietInfo>
+  <head><RESULT><CODE>INFO-000</CODE><MESSAGE>정상 처리되었습니다.</MESSAGE></RESULT></head>
+  <row>
+    <MLSV_YMD>20261019</MLSV_YMD>
+    <MMEAL_SC_CODE>3</MMEAL_SC_CODE>
+    <DDISH_NM>카레라이스(2.5.6)&lt;br/&gt;깍두기(9)</DDISH_NM>
+  </row>
+</mealServiceDietInfo>";
+
+        let meals = parse(src).unwrap();
+        assert_eq!(meals.len(), 1);
+        assert_eq!(meals[0].slot, MealSlot::Dinner);
+        assert_eq!(meals[0].dishes.len(), 2);
+        assert_eq!(meals[0].calories, None);
+    }
+
+    #[test]
+    fn reads_no_data_as_no_meals_and_fails_on_errors() {
+        let no_data =
+            r#"{"RESULT": {"CODE": "INFO-200", "MESSAGE": "해당하는 데이터가 없습니다."}}"#;
+        assert!(parse(no_data).unwrap().is_empty());
+
+        let error =
+            r#"{"RESULT": {"CODE": "ERROR-300", "MESSAGE": "필수 값이 누락되어 있습니다."}}"#;
+        assert!(matches!(parse(error), Err(Error::NeisError(_))));
+    }
+
+    #[test]
+    fn rejects_rows_it_cannot_place() {
+        let row = |ymd: &str, code: &str, dishes: &str| {
+            format!(
+                r#"{{"mealServiceDietInfo": [{{"row": [{{"MLSV_YMD": "{}", "MMEAL_SC_CODE": "{}", "DDISH_NM": "{}"}}]}}]}}"#,
+                ymd, code, dishes
+            )
+        };
+
+        for src in [
+            row("2026-10-19", "2", "쌀밥"),
+            row("20261019", "4", "쌀밥"),
+            row("20261019", "2", "<br/>"),
+        ] {
+            assert!(matches!(parse(&src), Err(Error::NeisError(_))), "{}", src);
+        }
+    }
+}
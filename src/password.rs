@@ -0,0 +1,77 @@
+use argon2::{
+    password_hash::{
+        self, rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
+    Argon2,
+};
+
+use crate::error::{Error, Result};
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Hashes a password with Argon2id (default parameters)
+/// * Returns PHC string format, which carries its own salt and parameters
+/// * Returns argon2's own error so it can be sent out of `web::block`
+pub fn hash_password(password: &str) -> std::result::Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// Verifies a password against a PHC string made by `hash_password`
+/// * Malformed hashes are treated as a mismatch
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub fn check_password_strength(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        Err(Error::WeakPassword)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_its_own_hash() {
+        let hash = hash_password("correct horse").unwrap();
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("correct horsE", &hash));
+    }
+
+    #[test]
+    fn salts_every_hash() {
+        assert_ne!(
+            hash_password("same password").unwrap(),
+            hash_password("same password").unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_hash_is_a_mismatch() {
+        assert!(!verify_password("password", ""));
+        assert!(!verify_password("password", "password"));
+    }
+
+    #[test]
+    fn strength_counts_characters() {
+        assert!(check_password_strength("1234567").is_err());
+        assert!(check_password_strength("12345678").is_ok());
+        // 8 characters, 24 bytes
+        assert!(check_password_strength("급식급식급식급식").is_ok());
+        assert!(check_password_strength("급식급식급식급").is_err());
+    }
+}
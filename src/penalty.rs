@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap};
+
+use actix_web::web::Data;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock,
+    db::{
+        models::{application::ApplicationDAO, suspension::SuspensionDAO, user::UserDAO},
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+/// `penalty` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PenaltyConfig {
+    pub enabled: bool,
+    /// No-shows within `window_days` that trigger a suspension
+    pub max_no_shows: u32,
+    pub window_days: i64,
+    /// How long applications stay suspended, today included
+    pub suspend_days: i64,
+    /// Also turn auto_apply off for the suspended user
+    pub disable_auto_apply: bool,
+    /// No-shows before this date are forgiven, e.g. from before the policy was announced
+    pub count_from: Option<NaiveDate>,
+}
+
+impl Default for PenaltyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_no_shows: 3,
+            window_days: 30,
+            suspend_days: 7,
+            disable_auto_apply: false,
+            count_from: None,
+        }
+    }
+}
+
+impl PenaltyConfig {
+    /// First day whose no-shows count on `today`, before earlier suspensions are considered
+    fn window_start(&self, today: NaiveDate) -> NaiveDate {
+        let from = today - Duration::days(self.window_days);
+        self.count_from
+            .map_or(from, |count_from| from.max(count_from))
+    }
+}
+
+/// Fails with `Error::Suspended` when the user may not apply today
+pub async fn ensure_not_suspended(
+    pool: Data<DbPool>,
+    config: &PenaltyConfig,
+    user: &UserDAO,
+) -> Result<()> {
+    match enforce(pool, config, vec![user.id]).await?.remove(&user.id) {
+        Some(until) => Err(Error::Suspended(until)),
+        None => Ok(()),
+    }
+}
+
+/// Last suspended day of each user among `user_ids` who may not apply today
+/// * First suspends the users whose recent no-shows reached `max_no_shows`, all in one transaction
+/// * Suspensions set by a teacher apply even with the policy disabled
+pub async fn enforce(
+    pool: Data<DbPool>,
+    config: &PenaltyConfig,
+    user_ids: Vec<u64>,
+) -> Result<HashMap<u64, NaiveDate>> {
+    let today = clock::today();
+
+    let mut suspended = SuspensionDAO::active_until(pool.clone(), user_ids.clone(), today).await?;
+    if !config.enabled {
+        return Ok(suspended);
+    }
+
+    let user_ids = user_ids
+        .into_iter()
+        .filter(|user_id| !suspended.contains_key(user_id))
+        .collect::<Vec<_>>();
+    let from = config.window_start(today);
+    let to = today - Duration::days(1);
+    if user_ids.is_empty() || from > to {
+        return Ok(suspended);
+    }
+
+    let last_imposed = SuspensionDAO::last_imposed(pool.clone(), user_ids.clone()).await?;
+    let no_shows = ApplicationDAO::no_shows(pool.clone(), user_ids, from, to).await?;
+    let due = due(config, today, &last_imposed, &no_shows);
+    if due.is_empty() {
+        return Ok(suspended);
+    }
+
+    let ends_on = today + Duration::days(config.suspend_days - 1);
+    suspended.extend(due.iter().map(|&(user_id, _)| (user_id, ends_on)));
+    SuspensionDAO::impose_for_no_shows(pool, due, today, ends_on, config.disable_auto_apply)
+        .await?;
+
+    Ok(suspended)
+}
+
+/// Users whose no-shows reached `max_no_shows` on `today`, with how many they had
+/// * Counts no-shows from the window start through yesterday
+/// * No-shows up to the day of a user's last suspension led to it or were forgiven, so the
+///   count starts again the day after
+fn due(
+    config: &PenaltyConfig,
+    today: NaiveDate,
+    last_imposed: &HashMap<u64, NaiveDate>,
+    no_shows: &[(u64, NaiveDate)],
+) -> Vec<(u64, u32)> {
+    let window_start = config.window_start(today);
+
+    let mut counts: BTreeMap<u64, u32> = BTreeMap::new();
+    for &(user_id, meal_date) in no_shows {
+        let from = match last_imposed.get(&user_id) {
+            Some(&imposed_on) => window_start.max(imposed_on + Duration::days(1)),
+            None => window_start,
+        };
+        if from <= meal_date && meal_date < today {
+            *counts.entry(user_id).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|&(_, no_shows)| no_shows >= config.max_no_shows)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PenaltyConfig {
+        PenaltyConfig {
+            enabled: true,
+            max_no_shows: 2,
+            window_days: 10,
+            ..PenaltyConfig::default()
+        }
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2026, 10, day)
+    }
+
+    #[test]
+    fn counts_no_shows_in_the_window() {
+        let today = day(20);
+        let no_shows = [
+            (1, day(9)),
+            (1, day(10)),
+            (1, day(19)),
+            (2, day(12)),
+            (2, day(12)),
+            (3, day(15)),
+            (3, day(20)),
+        ];
+
+        assert_eq!(
+            due(&config(), today, &HashMap::new(), &no_shows),
+            vec![(1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn starts_counting_the_day_after_the_last_suspension() {
+        let today = day(20);
+        let no_shows = [(1, day(14)), (1, day(15)), (1, day(16)), (1, day(17))];
+
+        let last_imposed = HashMap::from([(1, day(15))]);
+        assert_eq!(
+            due(&config(), today, &last_imposed, &no_shows),
+            vec![(1, 2)]
+        );
+
+        let last_imposed = HashMap::from([(1, day(16))]);
+        assert!(due(&config(), today, &last_imposed, &no_shows).is_empty());
+    }
+
+    #[test]
+    fn forgives_no_shows_before_count_from() {
+        let config = PenaltyConfig {
+            count_from: Some(day(15)),
+            ..config()
+        };
+        let no_shows = [(1, day(14)), (1, day(15)), (2, day(15)), (2, day(16))];
+
+        assert_eq!(
+            due(&config, day(20), &HashMap::new(), &no_shows),
+            vec![(2, 2)]
+        );
+        assert_eq!(config.window_start(day(30)), day(20));
+    }
+}
@@ -1,15 +1,39 @@
+mod allergy;
 mod apply;
+mod billing;
+mod check_in;
+mod export;
 mod loginout;
 mod meal;
 mod rate;
+mod session;
+mod survey;
+mod suspension;
 mod test;
 mod user;
 
-pub use apply::{apply_route, get_applications_route, ApplyParam, GetApplicationParam};
-pub use loginout::{login_route, logout_route};
+pub use allergy::{allergens_route, allergy_report_route};
+pub use apply::{
+    apply_route, get_applications_route, has_applied_route, headcount_route, roster_route,
+    ApplicationFilterParam, ApplyParam,
+};
+pub use billing::{
+    add_exemption_route, billing_summary_route, close_billing_route, end_exemption_route,
+    exemptions_route, meal_prices_route, set_meal_price_route, statement_route,
+};
+pub use check_in::{check_in_code_route, check_in_route, no_show_route};
+pub use export::{export_applications_route, export_billing_route, export_rates_route};
+pub use loginout::{change_password_route, login_route, logout_route};
+pub use meal::{
+    delete_meal_route, get_meal_range_route, get_meal_route, import_meal_route,
+    meal_capacity_route, post_meal_route, Dish,
+};
 pub use rate::{
-    get_rates_route, get_user_rate_route, post_rate_route, rank_route, GetRatesParam,
-    GetUserRatesParam, Rate, RateLevel, RateParam,
+    get_rates_route, get_user_rate_route, post_rate_route, rank_route, GetUserRatesParam, Rate,
+    RateLevel, RateParam,
 };
+pub use session::{revoke_session_route, sessions_route};
+pub use survey::{create_survey_template_route, survey_route, survey_templates_route};
+pub use suspension::{suspension_route, suspensions_route};
 pub use test::test_route;
-pub use user::user_route;
+pub use user::{set_allergies_route, user_route};
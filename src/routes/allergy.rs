@@ -0,0 +1,108 @@
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    allergen::Allergen,
+    auth::{AuthUser, TeacherUser},
+    clock,
+    db::{
+        models::{application::ApplicationDAO, meal::MealSlot, meal_item::MealItemDAO},
+        DbPool,
+    },
+    error::Result,
+};
+
+#[derive(Serialize)]
+pub struct AllergensResponse {
+    is_error: bool,
+    allergens: Vec<Allergen>,
+}
+
+/// Allergen codes and their names
+#[get("/allergens")]
+pub async fn allergens_route(_user: AuthUser) -> Result<HttpResponse> {
+    Ok(HttpResponse::Accepted().json(AllergensResponse {
+        is_error: false,
+        allergens: Allergen::all(),
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AllergyReportParam {
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    slot: MealSlot,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ExposedStudent {
+    username: String,
+    name: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Exposure {
+    allergen: Allergen,
+    /// Dishes of the day containing the allergen
+    dishes: Vec<String>,
+    /// Applied students allergic to it
+    students: Vec<ExposedStudent>,
+}
+
+#[derive(Serialize)]
+pub struct AllergyReportResponse {
+    is_error: bool,
+    date: NaiveDate,
+    slot: MealSlot,
+    exposures: Vec<Exposure>,
+}
+
+/// Which applied students meet which allergen in a meal (today's lunch if omitted),
+/// so the kitchen can prepare substitutes
+#[get("/allergy_report")]
+pub async fn allergy_report_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<AllergyReportParam>,
+) -> Result<HttpResponse> {
+    let date = param.date.unwrap_or_else(clock::today);
+    let items = MealItemDAO::by_meal(pool.clone(), date, param.slot).await?;
+    let applicants = ApplicationDAO::applicants(pool, date, param.slot).await?;
+
+    let mut exposures = Vec::new();
+    for allergen in Allergen::all() {
+        let dishes = items
+            .iter()
+            .filter(|item| item.allergen_codes().contains(&allergen.code))
+            .map(|item| item.name.clone())
+            .collect::<Vec<_>>();
+        let students = applicants
+            .iter()
+            .filter(|user| user.allergy_codes().contains(&allergen.code))
+            .map(|user| ExposedStudent {
+                username: user.username.clone(),
+                name: user.name.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if !dishes.is_empty() && !students.is_empty() {
+            exposures.push(Exposure {
+                allergen,
+                dishes,
+                students,
+            });
+        }
+    }
+
+    Ok(HttpResponse::Accepted().json(AllergyReportResponse {
+        is_error: false,
+        date,
+        slot: param.slot,
+        exposures,
+    }))
+}
@@ -1,101 +1,449 @@
 use actix_web::{
-    post,
-    web::{Data, Json},
+    get, post,
+    web::{Data, Json, Query},
     HttpResponse,
 };
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::{
+    auth::{AuthUser, TeacherUser},
+    clock,
     db::{
         models::{
-            application::{Application, ApplicationDAO},
-            user::UserDAO,
+            application::{
+                month_range, next_first_day_of_month, Application, ApplicationDAO,
+                ApplicationFilter, ApplyConfig, ApplyOutcome,
+            },
+            meal::MealSlot,
+            meal_item::MealItemDAO,
         },
         DbPool,
     },
-    error::Error,
+    penalty::{self, PenaltyConfig},
+    routes::Dish,
 };
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ApplyParam {
-    pub session_id: String,
     pub apply: bool,
-    pub send_date: NaiveDate,
+    /// Date of the meal
+    pub date: Option<NaiveDate>,
+    #[serde(default)]
+    pub slot: MealSlot,
+    /// Older clients send only this, meaning today's meal
+    pub send_date: Option<NaiveDate>,
+}
+
+impl ApplyParam {
+    pub fn meal_date(&self) -> Result<NaiveDate> {
+        self.date
+            .or(self.send_date)
+            .ok_or_else(|| Error::InvalidParam(String::from("`date` is missing")))
+    }
 }
 
 #[derive(Serialize)]
 pub struct ApplyResponse {
     is_error: bool,
+    #[serde(flatten)]
+    outcome: ApplyOutcome,
+    /// Dishes of the meal containing an allergen of the user
+    allergy_warnings: Vec<Dish>,
 }
 
+/// Applies for or cancels a meal; `applied` in the response is the resulting state
 #[post("/apply")]
-pub async fn apply_route(pool: Data<DbPool>, param: Json<ApplyParam>) -> Result<HttpResponse> {
-    //check session_id
-    UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
+pub async fn apply_route(
+    pool: Data<DbPool>,
+    config: Data<ApplyConfig>,
+    penalty_config: Data<PenaltyConfig>,
+    user: AuthUser,
+    param: Json<ApplyParam>,
+) -> Result<HttpResponse> {
+    // Cancelling stays open to suspended users
+    if param.apply {
+        penalty::ensure_not_suspended(pool.clone(), &penalty_config, &user).await?;
+    }
+
+    let outcome = ApplicationDAO::post(pool.clone(), &config, &user, param.0.clone()).await?;
 
-    ApplicationDAO::post(pool, param.0.clone()).await?;
+    let allergy_warnings = if outcome.applied {
+        let allergies = user.allergy_codes();
+        MealItemDAO::by_meal(pool, param.meal_date()?, param.slot)
+            .await?
+            .into_iter()
+            .map(|item| Dish::new(item, &allergies))
+            .filter(Dish::has_warning)
+            .collect()
+    } else {
+        Vec::new()
+    };
 
-    Ok(HttpResponse::Accepted().json(ApplyResponse { is_error: false }))
+    Ok(HttpResponse::Accepted().json(ApplyResponse {
+        is_error: false,
+        outcome,
+        allergy_warnings,
+    }))
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-pub struct GetApplicationParam {
-    pub session_id: String,
+/// Filters of the teacher reports; the current month when no dates are given
+#[derive(Clone, Deserialize)]
+pub struct ApplicationFilterParam {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    /// A whole month, as "YYYY-MM"
+    month: Option<String>,
+    username: Option<String>,
+    slot: Option<MealSlot>,
+}
+
+impl ApplicationFilterParam {
+    pub fn into_filter(self) -> Result<ApplicationFilter> {
+        let (from, to) = match (&self.month, self.from, self.to) {
+            (Some(month), _, _) => month_range(month)?,
+            (None, None, None) => {
+                let today = clock::today();
+                month_range(&today.format("%Y-%m").to_string())?
+            }
+            (None, from, to) => {
+                let from = from.or(to).unwrap_or_else(clock::today);
+                (from, to.unwrap_or(from))
+            }
+        };
+        if from > to {
+            return Err(Error::InvalidParam(format!("{} is after {}", from, to)));
+        }
+
+        Ok(ApplicationFilter {
+            from,
+            to,
+            username: self.username,
+            slot: self.slot,
+        })
+    }
 }
 
 #[derive(Serialize)]
 pub struct GetApplicationResponse {
     is_error: bool,
+    from: NaiveDate,
+    to: NaiveDate,
     applications: Vec<Application>,
 }
 
-#[post("/applications")]
+/// Active applications in a date range, ordered by grade, class and username
+#[get("/applications")]
 pub async fn get_applications_route(
     pool: Data<DbPool>,
-    param: Json<GetApplicationParam>,
+    _teacher: TeacherUser,
+    param: Query<ApplicationFilterParam>,
+) -> Result<HttpResponse> {
+    let filter = param.into_inner().into_filter()?;
+    let (from, to) = (filter.from, filter.to);
+
+    let applications = ApplicationDAO::roster(pool, filter)
+        .await?
+        .into_iter()
+        .map(Application::from)
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(GetApplicationResponse {
+        is_error: false,
+        from,
+        to,
+        applications,
+    }))
+}
+
+#[derive(Clone, Serialize)]
+pub struct RosterMeal {
+    date: NaiveDate,
+    slot: MealSlot,
+    applied_at: NaiveDateTime,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RosterStudent {
+    username: String,
+    name: String,
+    meals: Vec<RosterMeal>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RosterClass {
+    grade: Option<u8>,
+    class: Option<u8>,
+    students: Vec<RosterStudent>,
+}
+
+#[derive(Serialize)]
+struct RosterResponse {
+    is_error: bool,
+    from: NaiveDate,
+    to: NaiveDate,
+    classes: Vec<RosterClass>,
+}
+
+/// Applicants grouped by grade and class, each with the meals they applied for
+#[get("/applications/roster")]
+pub async fn roster_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<ApplicationFilterParam>,
 ) -> Result<HttpResponse> {
-    //check session_id
-    let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
+    let filter = param.into_inner().into_filter()?;
+    let (from, to) = (filter.from, filter.to);
 
-    if user.is_teacher {
-        let applications = ApplicationDAO::get(pool.clone(), param.0.clone()).await?;
+    // Rows come ordered by grade, class and username, so groups are consecutive
+    let mut classes: Vec<RosterClass> = Vec::new();
+    for (application, user) in ApplicationDAO::roster(pool, filter).await? {
+        let meal = RosterMeal {
+            date: application.meal_date,
+            slot: application.meal_slot(),
+            applied_at: application.created_at,
+        };
 
-        let mut apps = Vec::new();
-        for app in applications {
-            apps.push(Application::from_application_dto(pool.clone(), app).await?)
+        let class = match classes.last_mut() {
+            Some(class) if class.grade == user.grade && class.class == user.class => class,
+            _ => {
+                classes.push(RosterClass {
+                    grade: user.grade,
+                    class: user.class,
+                    students: Vec::new(),
+                });
+                classes.last_mut().unwrap()
+            }
+        };
+        match class.students.last_mut() {
+            Some(student) if student.username == user.username => student.meals.push(meal),
+            _ => class.students.push(RosterStudent {
+                username: user.username,
+                name: user.name,
+                meals: vec![meal],
+            }),
         }
+    }
 
-        Ok(HttpResponse::Accepted().json(GetApplicationResponse {
-            is_error: false,
-            applications: apps,
-        }))
-    } else {
-        Err(Error::Unprivileged)
+    Ok(HttpResponse::Accepted().json(RosterResponse {
+        is_error: false,
+        from,
+        to,
+        classes,
+    }))
+}
+
+#[derive(Clone, Serialize)]
+pub struct MealHeadcount {
+    date: NaiveDate,
+    slot: MealSlot,
+    applications: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MonthHeadcount {
+    /// "YYYY-MM"
+    month: String,
+    slot: MealSlot,
+    applications: i64,
+    /// Students who applied at least once
+    students: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClassHeadcount {
+    grade: Option<u8>,
+    class: Option<u8>,
+    slot: MealSlot,
+    applications: i64,
+    students: i64,
+}
+
+#[derive(Serialize)]
+struct HeadcountResponse {
+    is_error: bool,
+    from: NaiveDate,
+    to: NaiveDate,
+    meals: Vec<MealHeadcount>,
+    months: Vec<MonthHeadcount>,
+    classes: Vec<ClassHeadcount>,
+}
+
+/// Headcounts per meal, per month and per grade and class
+#[get("/applications/headcount")]
+pub async fn headcount_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<ApplicationFilterParam>,
+) -> Result<HttpResponse> {
+    let filter = param.into_inner().into_filter()?;
+    let (from, to) = (filter.from, filter.to);
+
+    let meals = ApplicationDAO::headcount_by_meal(pool.clone(), filter.clone())
+        .await?
+        .into_iter()
+        .map(|(date, slot, applications)| MealHeadcount {
+            date,
+            slot: MealSlot::from_code(slot).unwrap_or_default(),
+            applications,
+        })
+        .collect();
+
+    // Distinct students don't add up across months, so each month is counted on its own
+    let mut months = Vec::new();
+    let mut first_day = from;
+    while first_day <= to {
+        let next_first_day = next_first_day_of_month(first_day.year(), first_day.month());
+        let month_filter = ApplicationFilter {
+            from: first_day,
+            to: next_first_day.pred().min(to),
+            ..filter.clone()
+        };
+        let month = first_day.format("%Y-%m").to_string();
+        for (slot, applications, students) in
+            ApplicationDAO::headcount_by_slot(pool.clone(), month_filter).await?
+        {
+            months.push(MonthHeadcount {
+                month: month.clone(),
+                slot: MealSlot::from_code(slot).unwrap_or_default(),
+                applications,
+                students,
+            });
+        }
+        first_day = next_first_day;
     }
+
+    let classes = ApplicationDAO::headcount_by_class(pool, filter)
+        .await?
+        .into_iter()
+        .map(
+            |(grade, class, slot, applications, students)| ClassHeadcount {
+                grade,
+                class,
+                slot: MealSlot::from_code(slot).unwrap_or_default(),
+                applications,
+                students,
+            },
+        )
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(HeadcountResponse {
+        is_error: false,
+        from,
+        to,
+        meals,
+        months,
+        classes,
+    }))
 }
 
 #[derive(Clone, Deserialize)]
 pub struct HasAppliedParam {
-    session_id: String,
+    /// A day (today if neither is given)
+    date: Option<NaiveDate>,
+    /// Only this meal of `date`
+    slot: Option<MealSlot>,
+    /// A whole month, as "YYYY-MM"
+    month: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ApplicationStatus {
+    date: NaiveDate,
+    slot: MealSlot,
+    applied: bool,
+    /// Applied, but waiting for a seat
+    waitlisted: bool,
+    /// 1 for the first in line
+    waitlist_position: Option<u32>,
+    /// When the application was last made
+    applied_at: Option<NaiveDateTime>,
+    cancelled_at: Option<NaiveDateTime>,
+    /// Applying and cancelling are possible until then
+    deadline: NaiveDateTime,
+    changeable: bool,
+}
+
+impl ApplicationStatus {
+    fn new(
+        config: &ApplyConfig,
+        date: NaiveDate,
+        slot: MealSlot,
+        application: Option<&ApplicationDAO>,
+    ) -> Self {
+        let deadline = config.deadline(date, slot);
+        Self {
+            date,
+            slot,
+            applied: application.is_some_and(|app| app.cancelled_at.is_none()),
+            waitlisted: application.is_some_and(|app| app.waitlisted),
+            waitlist_position: None,
+            applied_at: application.map(|app| app.created_at),
+            cancelled_at: application.and_then(|app| app.cancelled_at),
+            deadline,
+            changeable: clock::now() < deadline,
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct HasAppliedResponse {
     is_error: bool,
-    applied: bool,
+    applications: Vec<ApplicationStatus>,
 }
 
-#[post("/has_applied")]
+/// Application status of the requesting user
+/// * With `date`, every meal of the day (or just `slot`), applied or not
+/// * With `month`, only the meals the user applied for or cancelled
+#[get("/has_applied")]
 pub async fn has_applied_route(
     pool: Data<DbPool>,
-    param: Json<HasAppliedParam>,
+    config: Data<ApplyConfig>,
+    user: AuthUser,
+    param: Query<HasAppliedParam>,
 ) -> Result<HttpResponse> {
+    let (from, to, rows, mut applications) = if let Some(month) = &param.month {
+        let (first_day, last_day) = month_range(month)?;
+
+        let rows = ApplicationDAO::by_user(pool.clone(), user.id, first_day, last_day).await?;
+        let applications = rows
+            .iter()
+            .map(|app| ApplicationStatus::new(&config, app.meal_date, app.meal_slot(), Some(app)))
+            .collect::<Vec<_>>();
+        (first_day, last_day, rows, applications)
+    } else {
+        let date = param.date.unwrap_or_else(clock::today);
+        let slots = match param.slot {
+            Some(slot) => vec![slot],
+            None => vec![MealSlot::Breakfast, MealSlot::Lunch, MealSlot::Dinner],
+        };
+
+        let rows = ApplicationDAO::by_user(pool.clone(), user.id, date, date).await?;
+        let applications = slots
+            .into_iter()
+            .map(|slot| {
+                let application = rows.iter().find(|app| app.meal_slot() == slot);
+                ApplicationStatus::new(&config, date, slot, application)
+            })
+            .collect::<Vec<_>>();
+        (date, date, rows, applications)
+    };
+
+    if rows.iter().any(|app| app.waitlisted) {
+        let positions = ApplicationDAO::waitlist_positions(pool, user.id, from, to).await?;
+        for app in &rows {
+            if let Some(status) = applications
+                .iter_mut()
+                .find(|status| status.date == app.meal_date && status.slot == app.meal_slot())
+            {
+                status.waitlist_position = positions.get(&app.id).copied();
+            }
+        }
+    }
+
     Ok(HttpResponse::Accepted().json(HasAppliedResponse {
         is_error: false,
-        applied: ApplicationDAO::get_one(pool, param.session_id.clone())
-            .await
-            .is_err(),
+        applications,
     }))
 }
@@ -0,0 +1,325 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json, Query},
+    HttpResponse,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{AuthUser, TeacherUser},
+    billing::{self, Billing, BillingConfig, Statement},
+    db::{
+        models::{
+            fee_exemption::FeeExemptionDAO, meal::MealSlot, meal_price::MealPriceDAO, user::UserDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+#[derive(Clone, Deserialize)]
+pub struct StatementParam {
+    /// "YYYY-MM"
+    month: String,
+    /// Teachers only; students get their own statement
+    username: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StatementResponse {
+    is_error: bool,
+    month: String,
+    closed_at: Option<NaiveDateTime>,
+    #[serde(flatten)]
+    statement: Statement,
+}
+
+/// A student's meal charges for a month
+#[get("/billing/statement")]
+pub async fn statement_route(
+    pool: Data<DbPool>,
+    config: Data<BillingConfig>,
+    user: AuthUser,
+    param: Query<StatementParam>,
+) -> Result<HttpResponse> {
+    let student = match &param.username {
+        Some(username) if *username != user.username => {
+            if !user.is_teacher {
+                return Err(Error::Unprivileged);
+            }
+            UserDAO::by_username(pool.clone(), username.clone()).await?
+        }
+        _ => user.user.clone(),
+    };
+
+    let mut billing = billing::statement(pool, &config, &param.month, student).await?;
+
+    Ok(HttpResponse::Accepted().json(StatementResponse {
+        is_error: false,
+        month: billing.month.format("%Y-%m").to_string(),
+        closed_at: billing.closed_at,
+        statement: billing.statements.remove(0),
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct BillingMonthParam {
+    /// "YYYY-MM"
+    month: String,
+}
+
+#[derive(Serialize)]
+struct StudentTotal {
+    username: String,
+    name: String,
+    grade: Option<u8>,
+    class: Option<u8>,
+    meals: u32,
+    exempt_meals: u32,
+    amount: u64,
+}
+
+#[derive(Serialize)]
+struct SummaryResponse {
+    is_error: bool,
+    month: String,
+    closed_at: Option<NaiveDateTime>,
+    meals: u32,
+    exempt_meals: u32,
+    /// In won
+    total: u64,
+    students: Vec<StudentTotal>,
+}
+
+impl From<Billing> for SummaryResponse {
+    fn from(billing: Billing) -> Self {
+        let total = billing.total();
+        let students = billing
+            .statements
+            .into_iter()
+            .map(|statement| StudentTotal {
+                username: statement.username,
+                name: statement.name,
+                grade: statement.grade,
+                class: statement.class,
+                meals: statement.meals,
+                exempt_meals: statement.exempt_meals,
+                amount: statement.amount,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            is_error: false,
+            month: billing.month.format("%Y-%m").to_string(),
+            closed_at: billing.closed_at,
+            meals: students.iter().map(|student| student.meals).sum(),
+            exempt_meals: students.iter().map(|student| student.exempt_meals).sum(),
+            total,
+            students,
+        }
+    }
+}
+
+/// Totals of every student for a month, for the office
+#[get("/billing/summary")]
+pub async fn billing_summary_route(
+    pool: Data<DbPool>,
+    config: Data<BillingConfig>,
+    _teacher: TeacherUser,
+    param: Query<BillingMonthParam>,
+) -> Result<HttpResponse> {
+    let billing = billing::month(pool, &config, &param.month).await?;
+
+    Ok(HttpResponse::Accepted().json(SummaryResponse::from(billing)))
+}
+
+/// Closes a month that has ended; its charges no longer change
+#[post("/billing/close")]
+pub async fn close_billing_route(
+    pool: Data<DbPool>,
+    config: Data<BillingConfig>,
+    teacher: TeacherUser,
+    param: Json<BillingMonthParam>,
+) -> Result<HttpResponse> {
+    let billing = billing::close(pool, &config, &param.month, teacher.id).await?;
+
+    Ok(HttpResponse::Accepted().json(SummaryResponse::from(billing)))
+}
+
+#[derive(Serialize)]
+struct BillingUpdateResponse {
+    is_error: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MealPriceParam {
+    starts_on: NaiveDate,
+    slot: MealSlot,
+    /// In won
+    price: u32,
+}
+
+#[derive(Serialize)]
+struct MealPrice {
+    starts_on: NaiveDate,
+    slot: MealSlot,
+    price: u32,
+}
+
+#[derive(Serialize)]
+struct MealPricesResponse {
+    is_error: bool,
+    /// Charged for dates before the first price of a slot
+    default_prices: BillingConfig,
+    prices: Vec<MealPrice>,
+}
+
+/// Every price set, oldest first
+#[get("/billing/prices")]
+pub async fn meal_prices_route(
+    pool: Data<DbPool>,
+    config: Data<BillingConfig>,
+    _teacher: TeacherUser,
+) -> Result<HttpResponse> {
+    let prices = MealPriceDAO::up_to(pool, NaiveDate::MAX)
+        .await?
+        .into_iter()
+        .map(|price| MealPrice {
+            starts_on: price.starts_on,
+            slot: price.meal_slot(),
+            price: price.price,
+        })
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(MealPricesResponse {
+        is_error: false,
+        default_prices: config.get_ref().clone(),
+        prices,
+    }))
+}
+
+/// Sets the price of a slot from `starts_on` until the next price of the slot
+#[post("/billing/prices")]
+pub async fn set_meal_price_route(
+    pool: Data<DbPool>,
+    teacher: TeacherUser,
+    param: Json<MealPriceParam>,
+) -> Result<HttpResponse> {
+    billing::ensure_open(pool.clone(), param.starts_on).await?;
+    MealPriceDAO::set(pool, param.starts_on, param.slot, param.price, teacher.id).await?;
+
+    Ok(HttpResponse::Accepted().json(BillingUpdateResponse { is_error: false }))
+}
+
+#[derive(Serialize)]
+struct Exemption {
+    id: u64,
+    username: String,
+    name: String,
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct ExemptionsResponse {
+    is_error: bool,
+    exemptions: Vec<Exemption>,
+}
+
+/// Every exemption, latest first
+#[get("/billing/exemptions")]
+pub async fn exemptions_route(pool: Data<DbPool>, _teacher: TeacherUser) -> Result<HttpResponse> {
+    let exemptions = FeeExemptionDAO::with_users(pool)
+        .await?
+        .into_iter()
+        .map(|(exemption, user)| Exemption {
+            id: exemption.id,
+            username: user.username,
+            name: user.name,
+            starts_on: exemption.starts_on,
+            ends_on: exemption.ends_on,
+            reason: exemption.reason,
+        })
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(ExemptionsResponse {
+        is_error: false,
+        exemptions,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExemptionParam {
+    username: String,
+    starts_on: NaiveDate,
+    /// Last exempt day; leave it out while it lasts
+    ends_on: Option<NaiveDate>,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Stops charging a student for meals from `starts_on` through `ends_on`
+#[post("/billing/exemptions")]
+pub async fn add_exemption_route(
+    pool: Data<DbPool>,
+    teacher: TeacherUser,
+    param: Json<ExemptionParam>,
+) -> Result<HttpResponse> {
+    let param = param.into_inner();
+    if param
+        .ends_on
+        .is_some_and(|ends_on| ends_on < param.starts_on)
+    {
+        return Err(Error::InvalidParam(String::from(
+            "`ends_on` is before `starts_on`",
+        )));
+    }
+    billing::ensure_open(pool.clone(), param.starts_on).await?;
+
+    let student = UserDAO::by_username(pool.clone(), param.username).await?;
+    FeeExemptionDAO::create(
+        pool,
+        student.id,
+        param.starts_on,
+        param.ends_on,
+        param.reason,
+        teacher.id,
+    )
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(BillingUpdateResponse { is_error: false }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct EndExemptionParam {
+    id: u64,
+    /// Last exempt day
+    ends_on: NaiveDate,
+}
+
+/// Sets the last exempt day of an exemption
+#[post("/billing/exemptions/end")]
+pub async fn end_exemption_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Json<EndExemptionParam>,
+) -> Result<HttpResponse> {
+    let exemption = FeeExemptionDAO::find(pool.clone(), param.id).await?;
+    if param.ends_on < exemption.starts_on {
+        return Err(Error::InvalidParam(format!(
+            "The exemption starts on {}",
+            exemption.starts_on
+        )));
+    }
+    // Ending it inside a closed month would rewrite that month's charges
+    billing::ensure_open(pool.clone(), param.ends_on).await?;
+    if let Some(ends_on) = exemption.ends_on {
+        billing::ensure_open(pool.clone(), ends_on).await?;
+    }
+
+    FeeExemptionDAO::end(pool, param.id, param.ends_on).await?;
+
+    Ok(HttpResponse::Accepted().json(BillingUpdateResponse { is_error: false }))
+}
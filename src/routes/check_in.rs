@@ -0,0 +1,147 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json, Query},
+    HttpResponse,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{AuthUser, TeacherUser},
+    clock,
+    db::{
+        models::{application::ApplicationDAO, meal::MealSlot, user::UserDAO},
+        DbPool,
+    },
+    error::Result,
+    routes::ApplicationFilterParam,
+    token::{CheckInConfig, CheckInToken, TokenKey},
+};
+
+#[derive(Serialize)]
+struct CheckInCodeResponse {
+    is_error: bool,
+    /// Shown as a QR code; fetch a new one before `expire_at`
+    code: String,
+    /// Unix timestamp
+    expire_at: i64,
+}
+
+/// A fresh check-in code for the requesting user
+#[get("/check_in/code")]
+pub async fn check_in_code_route(
+    key: Data<TokenKey>,
+    config: Data<CheckInConfig>,
+    user: AuthUser,
+) -> Result<HttpResponse> {
+    let (code, expire_at) = CheckInToken::generate(&user, &config, &key)?;
+
+    Ok(HttpResponse::Accepted().json(CheckInCodeResponse {
+        is_error: false,
+        code,
+        expire_at,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct CheckInParam {
+    /// Scanned from the student's QR code
+    code: String,
+    /// Meal being served today
+    #[serde(default)]
+    slot: MealSlot,
+}
+
+#[derive(Serialize)]
+struct CheckInResponse {
+    is_error: bool,
+    username: String,
+    name: String,
+    date: NaiveDate,
+    slot: MealSlot,
+    checked_in_at: NaiveDateTime,
+}
+
+/// Checks a student in for today's meal
+/// * Rejects expired codes, students without a seat and second scans
+#[post("/check_in")]
+pub async fn check_in_route(
+    pool: Data<DbPool>,
+    key: Data<TokenKey>,
+    teacher: TeacherUser,
+    param: Json<CheckInParam>,
+) -> Result<HttpResponse> {
+    let token = CheckInToken::decode(&param.code, &key)?;
+    let student = UserDAO::by_username(pool.clone(), token.user).await?;
+    let date = clock::today();
+
+    let checked_in_at =
+        ApplicationDAO::check_in(pool, &student, date, param.slot, teacher.id).await?;
+
+    Ok(HttpResponse::Accepted().json(CheckInResponse {
+        is_error: false,
+        username: student.username,
+        name: student.name,
+        date,
+        slot: param.slot,
+        checked_in_at,
+    }))
+}
+
+#[derive(Clone, Serialize)]
+pub struct Attendance {
+    username: String,
+    name: String,
+    grade: Option<u8>,
+    class: Option<u8>,
+    /// Meals the student had a seat for
+    applied: i64,
+    attended: i64,
+    no_shows: i64,
+}
+
+#[derive(Serialize)]
+struct NoShowResponse {
+    is_error: bool,
+    from: NaiveDate,
+    to: NaiveDate,
+    students: Vec<Attendance>,
+}
+
+/// Attendance of every student who had a seat, for meals already served
+/// * Takes the filters of /applications; today's meals are left out until tomorrow
+#[get("/check_in/no_shows")]
+pub async fn no_show_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<ApplicationFilterParam>,
+) -> Result<HttpResponse> {
+    let mut filter = param.into_inner().into_filter()?;
+    filter.to = filter.to.min(clock::today().pred());
+    let (from, to) = (filter.from, filter.to);
+
+    let students = if from > to {
+        Vec::new()
+    } else {
+        ApplicationDAO::attendance(pool, filter)
+            .await?
+            .into_iter()
+            .map(|(user, applied, attended)| Attendance {
+                username: user.username,
+                name: user.name,
+                grade: user.grade,
+                class: user.class,
+                applied,
+                attended,
+                no_shows: applied - attended,
+            })
+            .collect()
+    };
+
+    Ok(HttpResponse::Accepted().json(NoShowResponse {
+        is_error: false,
+        from,
+        to,
+        students,
+    }))
+}
@@ -0,0 +1,100 @@
+use actix_web::{
+    get,
+    http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType},
+    web::{Data, Query},
+    HttpResponse,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    auth::TeacherUser,
+    billing::BillingConfig,
+    db::DbPool,
+    error::Result,
+    export::{self, ExportFormat, Sheet},
+};
+
+#[derive(Clone, Deserialize)]
+pub struct ExportApplicationsParam {
+    /// "YYYY-MM"
+    month: String,
+    #[serde(default)]
+    format: ExportFormat,
+    /// Prepend a UTF-8 BOM to CSV so Excel shows Korean names correctly
+    #[serde(default)]
+    bom: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExportBillingParam {
+    /// "YYYY-MM"
+    month: String,
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    bom: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExportRatesParam {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    bom: bool,
+}
+
+/// CSV is streamed as it's encoded; XLSX is sent once the workbook is built
+async fn attachment(sheet: Sheet, format: ExportFormat, bom: bool) -> Result<HttpResponse> {
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(ContentType(format.content_type().parse().unwrap()))
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(sheet.file_name(format))],
+        });
+
+    Ok(match format {
+        ExportFormat::Csv => response.body(sheet.csv_body(bom)),
+        ExportFormat::Xlsx => response.body(sheet.into_xlsx().await?),
+    })
+}
+
+/// Applications of a month as a CSV or XLSX file
+#[get("/export/applications")]
+pub async fn export_applications_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<ExportApplicationsParam>,
+) -> Result<HttpResponse> {
+    let sheet = export::applications(pool, &param.month).await?;
+
+    attachment(sheet, param.format, param.bom).await
+}
+
+/// Dish ratings of a date range as a CSV or XLSX file
+#[get("/export/rates")]
+pub async fn export_rates_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<ExportRatesParam>,
+) -> Result<HttpResponse> {
+    let sheet = export::rates(pool, param.from, param.to).await?;
+
+    attachment(sheet, param.format, param.bom).await
+}
+
+/// Meal charges of a month per student as a CSV or XLSX file
+#[get("/export/billing")]
+pub async fn export_billing_route(
+    pool: Data<DbPool>,
+    config: Data<BillingConfig>,
+    _teacher: TeacherUser,
+    param: Query<ExportBillingParam>,
+) -> Result<HttpResponse> {
+    let sheet = export::billing(pool, &config, &param.month).await?;
+
+    attachment(sheet, param.format, param.bom).await
+}
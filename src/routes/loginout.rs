@@ -1,19 +1,31 @@
 use actix_web::{
+    cookie::{Cookie, SameSite},
+    http::header::USER_AGENT,
     post,
     web::{Data, Json},
-    HttpResponse,
+    HttpRequest, HttpResponse,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::{models::user::UserDAO, DbPool},
+    auth::{AuthUser, TOKEN_COOKIE},
+    db::{
+        models::{
+            session::{SessionConfig, SessionDAO},
+            user::UserDAO,
+        },
+        DbPool,
+    },
     error::Result,
+    token::{TokenKey, UserToken},
 };
 
 #[derive(Deserialize)]
 pub struct LoginParam {
     username: String,
-    // password: String,
+    password: String,
+    /// Label shown in the session list, e.g. "교실 태블릿"
+    device: Option<String>,
 }
 
 /// LoginResponse is used to send login result to the client    
@@ -22,30 +34,54 @@ pub struct LoginParam {
 #[derive(Serialize, Clone)]
 struct LoginResponse {
     is_error: bool,
-    session_id: String,
+    token: String,
     was_logged_in: bool,
 }
 
 /// Login procedure
 /// * Receives JSON request body as struct LoginParam
-/// * Responds with JSON body with session_id
+/// * Opens a new session; other sessions stay alive unless `session.allow_concurrent` is off
+/// * Responds with JSON body with a signed token bound to the new session
+/// * The same token is set as an HttpOnly cookie for browser clients
 #[post("/login")]
-pub async fn login_route(pool: Data<DbPool>, param: Json<LoginParam>) -> Result<HttpResponse> {
-    let (user, was_logged_in) = UserDAO::login(pool, param.username.clone()).await?;
+pub async fn login_route(
+    req: HttpRequest,
+    pool: Data<DbPool>,
+    key: Data<TokenKey>,
+    config: Data<SessionConfig>,
+    param: Json<LoginParam>,
+) -> Result<HttpResponse> {
+    let param = param.into_inner();
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(512).collect());
 
-    Ok(HttpResponse::Accepted().json(LoginResponse {
+    let (user, session, was_logged_in) = UserDAO::login(
+        pool,
+        &config,
+        param.username,
+        param.password,
+        param.device,
+        user_agent,
+    )
+    .await?;
+    let token = UserToken::generate(&user, &session, &config, &key)?;
+
+    let cookie = Cookie::build(TOKEN_COOKIE, token.clone())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .finish();
+
+    Ok(HttpResponse::Accepted().cookie(cookie).json(LoginResponse {
         is_error: false,
-        session_id: user.session_id.unwrap().clone(),
-        was_logged_in: was_logged_in,
+        token,
+        was_logged_in,
     }))
 }
 
-#[derive(Deserialize)]
-pub struct LogoutParam {
-    username: String,
-    session_id: String,
-}
-
 #[derive(Serialize)]
 pub struct LogoutResponse {
     is_error: bool,
@@ -53,14 +89,47 @@ pub struct LogoutResponse {
 }
 
 #[post("/logout")]
-pub async fn logout_route(pool: Data<DbPool>, param: Json<LogoutParam>) -> Result<HttpResponse> {
-    UserDAO::by_session_id(pool.clone(), &param.session_id)
-        .await?
-        .logout(pool)
+pub async fn logout_route(pool: Data<DbPool>, user: AuthUser) -> Result<HttpResponse> {
+    let AuthUser { user, session } = user;
+    SessionDAO::revoke(pool, session.id).await?;
+
+    let mut cookie = Cookie::named(TOKEN_COOKIE);
+    cookie.set_path("/");
+    cookie.make_removal();
+
+    Ok(HttpResponse::Accepted()
+        .cookie(cookie)
+        .json(LogoutResponse {
+            is_error: false,
+            username: user.username,
+        }))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordParam {
+    old_password: String,
+    new_password: String,
+}
+
+#[derive(Serialize)]
+pub struct ChangePasswordResponse {
+    is_error: bool,
+}
+
+/// Password change procedure
+/// * The current password must be given again, even with a valid token
+/// * Other sessions of the account are logged out; this one stays
+#[post("/change_password")]
+pub async fn change_password_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Json<ChangePasswordParam>,
+) -> Result<HttpResponse> {
+    let AuthUser { mut user, session } = user;
+    let param = param.into_inner();
+
+    user.change_password(pool, session.id, param.old_password, param.new_password)
         .await?;
 
-    Ok(HttpResponse::Accepted().json(LogoutResponse {
-        is_error: false,
-        username: param.username.clone(),
-    }))
+    Ok(HttpResponse::Accepted().json(ChangePasswordResponse { is_error: false }))
 }
@@ -0,0 +1,397 @@
+use actix_web::{
+    delete, get, post,
+    web::{Bytes, Data, Json, Query},
+    HttpResponse,
+};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    allergen,
+    auth::{AuthUser, TeacherUser},
+    clock,
+    db::{
+        models::{
+            application::{ApplicationDAO, ApplyConfig},
+            meal::{MealDAO, MealSlot},
+            meal_item::MealItemDAO,
+            meal_nutrient::MealNutrientDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+    neis::{self, ImportSummary},
+};
+
+/// Longest span /meals/range answers at once
+const MAX_RANGE_DAYS: i64 = 62;
+
+#[derive(Clone, Serialize)]
+pub struct Dish {
+    /// Referenced by RateReq.meal_item_id
+    id: u64,
+    name: String,
+    /// NEIS allergen codes
+    allergens: Vec<u8>,
+    /// Allergens of the dish the requesting user is allergic to
+    allergy_warnings: Vec<u8>,
+}
+
+impl Dish {
+    pub fn new(item: MealItemDAO, allergies: &[u8]) -> Self {
+        let allergens = item.allergen_codes();
+        Self {
+            id: item.id,
+            name: item.name,
+            allergy_warnings: allergen::common(&allergens, allergies),
+            allergens,
+        }
+    }
+
+    pub fn has_warning(&self) -> bool {
+        !self.allergy_warnings.is_empty()
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Menu {
+    date: NaiveDate,
+    slot: MealSlot,
+    dishes: Vec<Dish>,
+    calories: Option<f32>,
+    nutrients: Vec<MealNutrientDAO>,
+}
+
+impl Menu {
+    fn empty(date: NaiveDate, slot: MealSlot) -> Self {
+        Self {
+            date,
+            slot,
+            dishes: Vec::new(),
+            calories: None,
+            nutrients: Vec::new(),
+        }
+    }
+
+    /// Menus from `from` to `to` (both inclusive), by date and slot, with warnings for `allergies`
+    async fn load(
+        pool: Data<DbPool>,
+        from: NaiveDate,
+        to: NaiveDate,
+        allergies: &[u8],
+    ) -> Result<Vec<Self>> {
+        let meals = MealDAO::by_range(pool.clone(), from, to).await?;
+        let items = MealItemDAO::by_range(pool.clone(), from, to).await?;
+        let nutrients = MealNutrientDAO::by_range(pool, from, to).await?;
+
+        Ok(meals
+            .into_iter()
+            .map(|meal| Menu {
+                date: meal.date,
+                slot: meal.meal_slot(),
+                dishes: items
+                    .iter()
+                    .filter(|item| item.meal_id == meal.id)
+                    .cloned()
+                    .map(|item| Dish::new(item, allergies))
+                    .collect(),
+                calories: meal.calories,
+                nutrients: nutrients
+                    .iter()
+                    .filter(|nutrient| nutrient.meal_id == meal.id)
+                    .cloned()
+                    .collect(),
+            })
+            .collect())
+    }
+
+    async fn load_one(
+        pool: Data<DbPool>,
+        date: NaiveDate,
+        slot: MealSlot,
+        allergies: &[u8],
+    ) -> Result<Self> {
+        Ok(Self::load(pool, date, date, allergies)
+            .await?
+            .into_iter()
+            .find(|menu| menu.slot == slot)
+            .unwrap_or_else(|| Menu::empty(date, slot)))
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MealParam {
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    slot: MealSlot,
+}
+
+#[derive(Serialize)]
+pub struct MealResponse {
+    is_error: bool,
+    menu: Menu,
+}
+
+/// Menu of a meal (today's lunch if omitted)
+#[get("/meals")]
+pub async fn get_meal_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Query<MealParam>,
+) -> Result<HttpResponse> {
+    let date = param.date.unwrap_or_else(clock::today);
+
+    Ok(HttpResponse::Accepted().json(MealResponse {
+        is_error: false,
+        menu: Menu::load_one(pool, date, param.slot, &user.allergy_codes()).await?,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MealRangeParam {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+#[derive(Serialize)]
+pub struct MealRangeResponse {
+    is_error: bool,
+    menus: Vec<Menu>,
+}
+
+/// Menus from `from` to `to` (inclusive); dates without a menu are left out
+#[get("/meals/range")]
+pub async fn get_meal_range_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Query<MealRangeParam>,
+) -> Result<HttpResponse> {
+    if param.from > param.to {
+        return Err(Error::InvalidParam(String::from("`from` is after `to`")));
+    }
+    if param.to - param.from >= Duration::days(MAX_RANGE_DAYS) {
+        return Err(Error::InvalidParam(format!(
+            "Range is longer than {} days",
+            MAX_RANGE_DAYS
+        )));
+    }
+
+    Ok(HttpResponse::Accepted().json(MealRangeResponse {
+        is_error: false,
+        menus: Menu::load(pool, param.from, param.to, &user.allergy_codes()).await?,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct PostMealParam {
+    date: NaiveDate,
+    #[serde(default)]
+    slot: MealSlot,
+    dishes: Vec<PostDish>,
+}
+
+/// A dish name, or a dish with its NEIS allergen codes like `{"name": "우유", "allergens": [2]}`
+/// * A bare name keeps the codes the dish already has on the menu
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PostDish {
+    Name(String),
+    WithAllergens { name: String, allergens: Vec<u8> },
+}
+
+/// Trimmed dish names in order with their checked allergen codes
+/// * Listing a dish twice merges its codes, like a NEIS import does
+fn menu_dishes(posted: Vec<PostDish>) -> Result<Vec<(String, Option<Vec<u8>>)>> {
+    let mut dishes: Vec<(String, Option<Vec<u8>>)> = Vec::new();
+    for dish in posted {
+        let (name, allergens) = match dish {
+            PostDish::Name(name) => (name, None),
+            PostDish::WithAllergens { name, allergens } => {
+                (name, Some(allergen::normalize(&allergens)?))
+            }
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::InvalidParam(String::from("Dish name is empty")));
+        }
+
+        match dishes.iter_mut().find(|(same, _)| same == name) {
+            Some((_, same)) => {
+                if let Some(allergens) = allergens {
+                    let merged = same.get_or_insert_with(Vec::new);
+                    merged.extend(allergens);
+                    merged.sort_unstable();
+                    merged.dedup();
+                }
+            }
+            None => dishes.push((name.to_owned(), allergens)),
+        }
+    }
+    if dishes.is_empty() {
+        return Err(Error::InvalidParam(String::from("Menu has no dishes")));
+    }
+    Ok(dishes)
+}
+
+/// Publishes (or replaces) the menu of a meal
+#[post("/meals")]
+pub async fn post_meal_route(
+    pool: Data<DbPool>,
+    teacher: TeacherUser,
+    param: Json<PostMealParam>,
+) -> Result<HttpResponse> {
+    let param = param.into_inner();
+
+    let dishes = menu_dishes(param.dishes)?;
+    MealDAO::publish(pool.clone(), param.date, param.slot, dishes).await?;
+
+    Ok(HttpResponse::Accepted().json(MealResponse {
+        is_error: false,
+        menu: Menu::load_one(pool, param.date, param.slot, &teacher.allergy_codes()).await?,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ImportMealResponse {
+    is_error: bool,
+    #[serde(flatten)]
+    summary: ImportSummary,
+}
+
+/// Imports menus from an uploaded NEIS mealServiceDietInfo export (JSON or XML)
+/// * POST /meals/import; registered as a resource in main.rs so only it accepts uploads up to
+///   `neis::MAX_EXPORT_BYTES`
+pub async fn import_meal_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    body: Bytes,
+) -> Result<HttpResponse> {
+    let src = std::str::from_utf8(&body)
+        .map_err(|_| Error::NeisError(String::from("Upload is not UTF-8")))?;
+
+    Ok(HttpResponse::Accepted().json(ImportMealResponse {
+        is_error: false,
+        summary: neis::import(pool, src).await?,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MealCapacityParam {
+    date: NaiveDate,
+    #[serde(default)]
+    slot: MealSlot,
+    /// None falls back to the capacity of the slot in config.yml
+    capacity: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct MealCapacityResponse {
+    is_error: bool,
+    /// Seats now in effect; None is unlimited
+    capacity: Option<u32>,
+    /// Waitlisted applications admitted to the new seats
+    promoted: usize,
+}
+
+/// Sets the seats of a meal and admits waitlisted students if seats were added
+#[post("/meals/capacity")]
+pub async fn meal_capacity_route(
+    pool: Data<DbPool>,
+    config: Data<ApplyConfig>,
+    _teacher: TeacherUser,
+    param: Json<MealCapacityParam>,
+) -> Result<HttpResponse> {
+    if param.capacity == Some(0) {
+        return Err(Error::InvalidParam(String::from(
+            "Capacity must be at least 1; send null for the default",
+        )));
+    }
+
+    let promoted =
+        ApplicationDAO::set_capacity(pool, &config, param.date, param.slot, param.capacity).await?;
+
+    Ok(HttpResponse::Accepted().json(MealCapacityResponse {
+        is_error: false,
+        capacity: param.capacity.or(config.slot_config(param.slot).capacity),
+        promoted,
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct DeleteMealParam {
+    date: NaiveDate,
+    #[serde(default)]
+    slot: MealSlot,
+}
+
+#[derive(Serialize)]
+pub struct DeleteMealResponse {
+    is_error: bool,
+}
+
+/// Withdraws the menu of a meal
+/// * Ratings of its dishes are kept, unlinked from the dish
+#[delete("/meals")]
+pub async fn delete_meal_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+    param: Query<DeleteMealParam>,
+) -> Result<HttpResponse> {
+    if MealDAO::delete(pool, param.date, param.slot).await? {
+        Ok(HttpResponse::Accepted().json(DeleteMealResponse { is_error: false }))
+    } else {
+        Err(Error::NotFoundOnDB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn posted(json: &str) -> Vec<PostDish> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_names_and_dishes_with_allergens() {
+        let dishes = menu_dishes(posted(
+            r#"[" 쌀밥 ", {"name": "우유", "allergens": [2]}, {"name": "돈까스", "allergens": [10, 1, 5, 1]}]"#,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            dishes,
+            vec![
+                (String::from("쌀밥"), None),
+                (String::from("우유"), Some(vec![2])),
+                (String::from("돈까스"), Some(vec![1, 5, 10])),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_a_dish_listed_twice() {
+        let dishes = menu_dishes(posted(
+            r#"["김치", {"name": "김치", "allergens": [9]}, {"name": "김치 ", "allergens": [5, 9]}]"#,
+        ))
+        .unwrap();
+
+        assert_eq!(dishes, vec![(String::from("김치"), Some(vec![5, 9]))]);
+    }
+
+    #[test]
+    fn rejects_unknown_allergens_and_empty_menus() {
+        for json in [
+            r#"[{"name": "우유", "allergens": [19]}]"#,
+            r#"[{"name": "우유", "allergens": [0]}]"#,
+            r#"["  "]"#,
+            r#"[]"#,
+        ] {
+            assert!(
+                matches!(menu_dishes(posted(json)), Err(Error::InvalidParam(_))),
+                "{}",
+                json
+            );
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use actix_web::{
-    post,
-    web::{Data, Json},
+    get, post,
+    web::{Data, Json, Query},
     HttpResponse,
 };
 use chrono::{NaiveDate, NaiveDateTime};
@@ -11,17 +11,29 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::{
+    auth::AuthUser,
+    clock,
+    comment::CommentFilter,
     db::{
-        models::{rate::RateDAO, total_rate::TotalRateDAO, user::UserDAO},
+        models::{
+            meal::MealSlot,
+            rate::{RateDAO, RateOutcome},
+            total_rate::TotalRateDAO,
+        },
         DbPool,
     },
-    error::Result,
+    error::{Error, Result},
+    survey::{self, AnswerReq, SurveyResult},
 };
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct RateReq {
-    pub food_name: String,
+    /// Dish id from /meals
+    pub meal_item_id: u64,
     pub level: RateLevel,
+    /// Shown to teachers only
+    #[serde(default)]
+    pub comment: Option<String>,
 }
 
 #[derive(Clone, Deserialize_repr, Serialize_repr)]
@@ -32,49 +44,94 @@ pub enum RateLevel {
     Good = 2,
 }
 
-impl From<i8> for RateLevel {
-    fn from(level: i8) -> Self {
+impl TryFrom<i8> for RateLevel {
+    type Error = Error;
+
+    fn try_from(level: i8) -> Result<Self> {
         match level {
-            -1 => Self::Bad,
-            1 => Self::Soso,
-            2 => Self::Good,
-            _ => unimplemented!(),
+            -1 => Ok(Self::Bad),
+            1 => Ok(Self::Soso),
+            2 => Ok(Self::Good),
+            _ => Err(Error::InvalidRateLevel(level)),
+        }
+    }
+}
+
+impl RateLevel {
+    /// Closest level to a stored value, for repairing rows written before the CHECK constraint
+    pub fn nearest(level: i8) -> Self {
+        match level {
+            i8::MIN..=-1 => Self::Bad,
+            0 | 1 => Self::Soso,
+            2..=i8::MAX => Self::Good,
         }
     }
 }
 
 #[derive(Clone, Deserialize)]
 pub struct RateParam {
-    pub session_id: String,
+    #[serde(default)]
     pub rates: Vec<RateReq>,
-    pub total_rate: u8, // 0~10
+    /// From 0 to 10
+    pub total_rate: Option<u8>,
+    /// About the meal as a whole, stored with `total_rate`
+    #[serde(default)]
+    pub comment: Option<String>,
     pub send_date: NaiveDate,
+    /// Meal the survey answers are about
+    #[serde(default)]
+    pub slot: MealSlot,
+    /// Answers to the survey active for the meal
+    pub answers: Option<Vec<AnswerReq>>,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Serialize)]
 pub struct RateResponse {
     is_error: bool,
+    #[serde(flatten)]
+    outcome: RateOutcome,
 }
 
+/// Rates today's dishes and the day as a whole, and answers the survey of a meal
+/// * Posting again changes the earlier ratings and answers
+/// * Comments go through the length limit and word filter of the `comments` config
 #[post("/post_rate")]
-pub async fn post_rate_route(pool: Data<DbPool>, param: Json<RateParam>) -> Result<HttpResponse> {
-    RateDAO::post(pool.clone(), param.0.clone()).await?;
-    TotalRateDAO::post(pool, param.0.clone()).await?;
-    //TODO : UPDATE RATE IF EXISTS
+pub async fn post_rate_route(
+    pool: Data<DbPool>,
+    filter: Data<CommentFilter>,
+    user: AuthUser,
+    param: Json<RateParam>,
+) -> Result<HttpResponse> {
+    let mut param = param.into_inner();
+    param.comment = filter.check(param.comment.take())?;
+    for rate in &mut param.rates {
+        rate.comment = filter.check(rate.comment.take())?;
+    }
+    let survey = match param.answers.take() {
+        Some(answers) => Some(
+            survey::submission(pool.clone(), &filter, param.send_date, param.slot, answers).await?,
+        ),
+        None => None,
+    };
+    let outcome = RateDAO::post(pool, &user, param, survey).await?;
 
-    Ok(HttpResponse::Accepted().json(RateResponse { is_error: false }))
+    Ok(HttpResponse::Accepted().json(RateResponse {
+        is_error: false,
+        outcome,
+    }))
 }
 
 #[derive(Clone, Deserialize)]
 pub struct GetRatesParam {
-    session_id: String,
+    meal_item_id: Option<u64>,
     food_name: Option<String>,
     date: Option<NaiveDate>,
+    /// Limits survey answers to a meal
+    slot: Option<MealSlot>,
 }
 
 #[derive(Clone, Deserialize)]
 pub struct GetUserRatesParam {
-    pub session_id: String,
     pub username: String,
     pub date: Option<NaiveDate>,
 }
@@ -84,6 +141,18 @@ pub struct GetRatesResponse {
     is_error: bool,
     rates: Vec<Rate>,
     total_avg_rate: f32,
+    /// Survey answers of the day, with the template version each answers; empty unless a
+    /// teacher asks
+    surveys: Vec<SurveyResult>,
+    /// Comments on the day's meals, empty unless a teacher asks
+    comments: Vec<MealComment>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MealComment {
+    username: String,
+    comment: String,
+    created_at: NaiveDateTime,
 }
 
 #[derive(Clone, Serialize)]
@@ -96,34 +165,85 @@ pub struct GetUserRatesResponse {
 #[derive(Clone, Deserialize, Serialize, Queryable)]
 pub struct Rate {
     pub username: String,
+    pub meal_item_id: Option<u64>,
     pub food_name: String,
     pub rate_level: RateLevel,
     pub created_at: NaiveDateTime,
+    /// Only teachers see comments
+    pub comment: Option<String>,
 }
 
-impl From<&(String, String, i8, NaiveDateTime)> for Rate {
-    fn from(src: &(String, String, i8, NaiveDateTime)) -> Self {
-        Self {
+impl
+    TryFrom<&(
+        String,
+        Option<u64>,
+        String,
+        i8,
+        NaiveDateTime,
+        Option<String>,
+    )> for Rate
+{
+    type Error = Error;
+
+    fn try_from(
+        src: &(
+            String,
+            Option<u64>,
+            String,
+            i8,
+            NaiveDateTime,
+            Option<String>,
+        ),
+    ) -> Result<Self> {
+        Ok(Self {
             username: src.0.clone(),
-            food_name: src.1.clone(),
-            rate_level: RateLevel::from(src.2),
-            created_at: src.3,
-        }
+            meal_item_id: src.1,
+            food_name: src.2.clone(),
+            rate_level: RateLevel::try_from(src.3)?,
+            created_at: src.4,
+            comment: src.5.clone(),
+        })
     }
 }
 
-#[post("/get_rates")]
+#[get("/get_rates")]
 pub async fn get_rates_route(
     pool: Data<DbPool>,
-    param: Json<GetRatesParam>,
+    user: AuthUser,
+    param: Query<GetRatesParam>,
 ) -> Result<HttpResponse> {
-    //checking session_id
-    UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
+    let date = param.date.unwrap_or_else(clock::today);
+    let mut rates = RateDAO::get(pool.clone(), date).await?;
 
-    let rates = if let Some(date) = param.date {
-        RateDAO::get(pool.clone(), date).await?
+    // Answers carry usernames and free text, so students only see the ratings
+    let (surveys, comments) = if user.is_teacher {
+        let surveys = survey::results(pool.clone(), date, param.slot).await?;
+        let comments = TotalRateDAO::with_comments(pool.clone(), date)
+            .await?
+            .into_iter()
+            .filter_map(|(total_rate, user)| {
+                Some(MealComment {
+                    username: user.username,
+                    comment: total_rate.comment?,
+                    created_at: total_rate.created_at,
+                })
+            })
+            .collect();
+        (surveys, comments)
     } else {
-        RateDAO::get_today(pool.clone()).await?
+        for rate in &mut rates {
+            rate.comment = None;
+        }
+        (Vec::new(), Vec::new())
+    };
+
+    let rates = if let Some(meal_item_id) = param.meal_item_id {
+        rates
+            .into_iter()
+            .filter(|r| r.meal_item_id == Some(meal_item_id))
+            .collect::<Vec<_>>()
+    } else {
+        rates
     };
 
     let rates = if let Some(food_name) = param.food_name.clone() {
@@ -142,21 +262,24 @@ pub async fn get_rates_route(
         is_error: false,
         rates,
         total_avg_rate,
+        surveys,
+        comments,
     }))
 }
 
-#[post("/get_user_rate")]
+#[get("/get_user_rate")]
 pub async fn get_user_rate_route(
     pool: Data<DbPool>,
-    param: Json<GetUserRatesParam>,
+    user: AuthUser,
+    param: Query<GetUserRatesParam>,
 ) -> Result<HttpResponse> {
     let rates = if let Some(date) = param.date {
-        RateDAO::get_one(pool.clone(), param.0.clone(), date).await?
+        RateDAO::get_one(pool.clone(), &user, param.0.clone(), date).await?
     } else {
-        RateDAO::get_one_today(pool.clone(), param.0.clone()).await?
+        RateDAO::get_one_today(pool.clone(), &user, param.0.clone()).await?
     };
 
-    let total_rate = TotalRateDAO::get_one_today(pool, param.0.clone()).await?;
+    let total_rate = TotalRateDAO::get_one_today(pool, &user, param.0.clone()).await?;
 
     Ok(HttpResponse::Accepted().json(GetUserRatesResponse {
         is_error: false,
@@ -165,22 +288,14 @@ pub async fn get_user_rate_route(
     }))
 }
 
-#[derive(Deserialize)]
-pub struct RankParam {
-    session_id: String,
-}
-
 #[derive(Serialize)]
 pub struct RankResponse {
     is_error: bool,
     rank: HashMap<String, i32>,
 }
 
-#[post("/rank")]
-pub async fn rank_route(pool: Data<DbPool>, param: Json<RankParam>) -> Result<HttpResponse> {
-    //checking session_id
-    UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
-
+#[get("/rank")]
+pub async fn rank_route(pool: Data<DbPool>, _user: AuthUser) -> Result<HttpResponse> {
     let mut rank_hash: HashMap<String, i32> = HashMap::new();
 
     let rates = RateDAO::get_today(pool).await?;
@@ -194,3 +309,43 @@ pub async fn rank_route(pool: Data<DbPool>, param: Json<RankParam>) -> Result<Ht
         rank: rank_hash,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_the_three_levels() {
+        assert!(matches!(RateLevel::try_from(-1), Ok(RateLevel::Bad)));
+        assert!(matches!(RateLevel::try_from(1), Ok(RateLevel::Soso)));
+        assert!(matches!(RateLevel::try_from(2), Ok(RateLevel::Good)));
+
+        for level in [i8::MIN, -2, 0, 3, i8::MAX] {
+            assert!(matches!(
+                RateLevel::try_from(level),
+                Err(Error::InvalidRateLevel(invalid)) if invalid == level
+            ));
+        }
+    }
+
+    #[test]
+    fn repairs_to_the_nearest_level() {
+        let nearest = |level| RateLevel::nearest(level) as i8;
+
+        assert_eq!(nearest(i8::MIN), -1);
+        assert_eq!(nearest(-1), -1);
+        assert_eq!(nearest(0), 1);
+        assert_eq!(nearest(1), 1);
+        assert_eq!(nearest(2), 2);
+        assert_eq!(nearest(i8::MAX), 2);
+    }
+
+    #[test]
+    fn reads_levels_from_json_numbers() {
+        let rate = serde_json::from_str::<RateReq>(r#"{"meal_item_id": 1, "level": -1}"#);
+        assert!(matches!(rate.unwrap().level, RateLevel::Bad));
+
+        assert!(serde_json::from_str::<RateReq>(r#"{"meal_item_id": 1, "level": 0}"#).is_err());
+        assert!(serde_json::from_str::<RateReq>(r#"{"meal_item_id": 1, "level": 3}"#).is_err());
+    }
+}
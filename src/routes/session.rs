@@ -0,0 +1,82 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json},
+    HttpResponse,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::AuthUser,
+    db::{models::session::SessionDAO, DbPool},
+    error::Result,
+};
+
+#[derive(Clone, Serialize)]
+pub struct Session {
+    id: String,
+    device: Option<String>,
+    user_agent: Option<String>,
+    created_at: NaiveDateTime,
+    last_seen_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+    /// Whether this is the session the request came through
+    current: bool,
+}
+
+impl Session {
+    fn from_session_dao(original: SessionDAO, current_id: &str) -> Self {
+        Self {
+            current: original.id == current_id,
+            id: original.id,
+            device: original.device,
+            user_agent: original.user_agent,
+            created_at: original.created_at,
+            last_seen_at: original.last_seen_at,
+            expires_at: original.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SessionsResponse {
+    is_error: bool,
+    sessions: Vec<Session>,
+}
+
+/// Lists the caller's live sessions, most recently used first
+#[get("/sessions")]
+pub async fn sessions_route(pool: Data<DbPool>, user: AuthUser) -> Result<HttpResponse> {
+    let sessions = SessionDAO::by_user(pool, user.id)
+        .await?
+        .into_iter()
+        .map(|s| Session::from_session_dao(s, &user.session.id))
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(SessionsResponse {
+        is_error: false,
+        sessions,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct RevokeSessionParam {
+    session_id: String,
+}
+
+#[derive(Serialize)]
+pub struct RevokeSessionResponse {
+    is_error: bool,
+}
+
+/// Logs out one of the caller's sessions, e.g. a lost phone
+#[post("/revoke_session")]
+pub async fn revoke_session_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Json<RevokeSessionParam>,
+) -> Result<HttpResponse> {
+    SessionDAO::revoke_own(pool, user.id, param.session_id.clone()).await?;
+
+    Ok(HttpResponse::Accepted().json(RevokeSessionResponse { is_error: false }))
+}
@@ -0,0 +1,83 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json, Query},
+    HttpResponse,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{AuthUser, TeacherUser},
+    clock,
+    db::{models::meal::MealSlot, DbPool},
+    error::Result,
+    survey::{self, Template, TemplateParam},
+};
+
+#[derive(Clone, Deserialize)]
+pub struct SurveyParam {
+    /// Today when left out
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    slot: MealSlot,
+}
+
+#[derive(Serialize)]
+struct SurveyResponse {
+    is_error: bool,
+    /// None when no survey is asked for the meal
+    template: Option<Template>,
+}
+
+/// Survey to answer for a meal with /post_rate
+#[get("/survey")]
+pub async fn survey_route(
+    pool: Data<DbPool>,
+    _user: AuthUser,
+    param: Query<SurveyParam>,
+) -> Result<HttpResponse> {
+    let date = param.date.unwrap_or_else(clock::today);
+
+    Ok(HttpResponse::Accepted().json(SurveyResponse {
+        is_error: false,
+        template: survey::active(pool, date, param.slot).await?,
+    }))
+}
+
+#[derive(Serialize)]
+struct TemplatesResponse {
+    is_error: bool,
+    templates: Vec<Template>,
+}
+
+/// Every version of every survey template, latest version first
+#[get("/survey/templates")]
+pub async fn survey_templates_route(
+    pool: Data<DbPool>,
+    _teacher: TeacherUser,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Accepted().json(TemplatesResponse {
+        is_error: false,
+        templates: survey::templates(pool).await?,
+    }))
+}
+
+#[derive(Serialize)]
+struct TemplateResponse {
+    is_error: bool,
+    template: Template,
+}
+
+/// Creates a survey template, or the next version of one with the same name
+/// * Earlier versions stay as they were, so their answers keep their questions
+#[post("/survey/templates")]
+pub async fn create_survey_template_route(
+    pool: Data<DbPool>,
+    teacher: TeacherUser,
+    param: Json<TemplateParam>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Accepted().json(TemplateResponse {
+        is_error: false,
+        template: survey::create(pool, param.into_inner(), teacher.id).await?,
+    }))
+}
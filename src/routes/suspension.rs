@@ -0,0 +1,101 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json},
+    HttpResponse,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::TeacherUser,
+    clock,
+    db::{
+        models::{suspension::SuspensionDAO, user::UserDAO},
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+#[derive(Clone, Deserialize)]
+pub struct SuspensionParam {
+    username: String,
+    /// Last suspended day; leave it out to lift the suspension
+    until: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+struct SuspensionResponse {
+    is_error: bool,
+    username: String,
+    suspended_until: Option<NaiveDate>,
+}
+
+/// Suspends a student's applications from today through `until`, or lifts their suspension
+/// * Replaces any suspension in force, whether from the no-show policy or a teacher
+#[post("/suspension")]
+pub async fn suspension_route(
+    pool: Data<DbPool>,
+    teacher: TeacherUser,
+    param: Json<SuspensionParam>,
+) -> Result<HttpResponse> {
+    let student = UserDAO::by_username(pool.clone(), param.username.clone()).await?;
+    let today = clock::today();
+    if let Some(until) = param.until {
+        if until < today {
+            return Err(Error::InvalidParam(format!("{} is in the past", until)));
+        }
+    }
+
+    SuspensionDAO::replace(pool, student.id, today, param.until, teacher.id).await?;
+
+    Ok(HttpResponse::Accepted().json(SuspensionResponse {
+        is_error: false,
+        username: student.username,
+        suspended_until: param.until,
+    }))
+}
+
+#[derive(Serialize)]
+struct Suspension {
+    username: String,
+    name: String,
+    grade: Option<u8>,
+    class: Option<u8>,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+    /// No-shows that led to it; 0 when a teacher set it
+    no_shows: u32,
+    by_teacher: bool,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct SuspensionsResponse {
+    is_error: bool,
+    suspensions: Vec<Suspension>,
+}
+
+/// Suspensions in force today
+#[get("/suspensions")]
+pub async fn suspensions_route(pool: Data<DbPool>, _teacher: TeacherUser) -> Result<HttpResponse> {
+    let suspensions = SuspensionDAO::all_active(pool, clock::today())
+        .await?
+        .into_iter()
+        .map(|(suspension, user)| Suspension {
+            username: user.username,
+            name: user.name,
+            grade: user.grade,
+            class: user.class,
+            starts_on: suspension.starts_on,
+            ends_on: suspension.ends_on,
+            no_shows: suspension.no_shows,
+            by_teacher: suspension.imposed_by.is_some(),
+            created_at: suspension.created_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Accepted().json(SuspensionsResponse {
+        is_error: false,
+        suspensions,
+    }))
+}
@@ -1,19 +1,19 @@
 use actix_web::{
-    post,
-    web::{Data, Json},
+    get, post,
+    web::{Data, Json, Query},
     HttpResponse,
 };
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    auth::AuthUser,
     db::{models::user::UserDAO, DbPool},
-    error::{Result, Error},
+    error::{Error, Result},
 };
 
 #[derive(Clone, Deserialize)]
 pub struct UserParam {
-    session_id: String,
     username: String,
 }
 
@@ -27,6 +27,10 @@ struct UserResponse {
 pub struct User {
     username: String,
     name: String,
+    grade: Option<u8>,
+    class: Option<u8>,
+    /// Allergen codes (1~18)
+    allergies: Vec<u8>,
     auto_apply: bool,
     is_teacher: bool,
     created_at: NaiveDateTime,
@@ -37,6 +41,9 @@ impl From<UserDAO> for User {
         Self {
             username: u.username.clone(),
             name: u.name.clone(),
+            grade: u.grade,
+            class: u.class,
+            allergies: u.allergy_codes(),
             auto_apply: u.auto_apply,
             is_teacher: u.is_teacher,
             created_at: u.created_at,
@@ -44,15 +51,45 @@ impl From<UserDAO> for User {
     }
 }
 
-#[post("/user")]
-pub async fn user_route(pool: Data<DbPool>, param: Json<UserParam>) -> Result<HttpResponse> {
-    let user = UserDAO::by_session_id(pool.clone(), &param.session_id).await?;
-    if user.is_teacher || user.username == param.username {
-        Ok(HttpResponse::Accepted().json(UserResponse {
-            is_error: false,
-            user: user.into(),
-        }))
+#[get("/user")]
+pub async fn user_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Query<UserParam>,
+) -> Result<HttpResponse> {
+    let AuthUser { user, .. } = user;
+
+    let target = if user.username == param.username {
+        user
+    } else if user.is_teacher {
+        UserDAO::by_username(pool, &param.username).await?
     } else {
-        Err(Error::Unprivileged)
-    }   
+        return Err(Error::Unprivileged);
+    };
+
+    Ok(HttpResponse::Accepted().json(UserResponse {
+        is_error: false,
+        user: target.into(),
+    }))
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AllergiesParam {
+    allergies: Vec<u8>,
+}
+
+/// Replaces the allergies of the requesting user
+#[post("/user/allergies")]
+pub async fn set_allergies_route(
+    pool: Data<DbPool>,
+    user: AuthUser,
+    param: Json<AllergiesParam>,
+) -> Result<HttpResponse> {
+    let AuthUser { mut user, .. } = user;
+    user.set_allergies(pool, &param.allergies).await?;
+
+    Ok(HttpResponse::Accepted().json(UserResponse {
+        is_error: false,
+        user: user.into(),
+    }))
 }
@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+
+use actix_web::web::Data;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    comment::CommentFilter,
+    db::{
+        models::{
+            meal::MealSlot,
+            meal_item::MealItemDAO,
+            survey_answer::{NewAnswer, SurveyAnswerDAO},
+            survey_question::{NewQuestion, SurveyQuestionDAO},
+            survey_template::SurveyTemplateDAO,
+        },
+        DbPool,
+    },
+    error::{Error, Result},
+};
+
+/// Highest point of a Likert question when the template doesn't give one
+const DEFAULT_SCALE: u8 = 5;
+const MAX_SCALE: u8 = 10;
+const MAX_CHOICES: usize = 20;
+const MAX_TEXT_ANSWER_CHARS: usize = 500;
+
+/// Stored by its code in survey_questions.kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    /// A point from 1 to the question's scale
+    Likert = 1,
+    YesNo = 2,
+    /// One of the question's choices
+    Choice = 3,
+    Text = 4,
+}
+
+impl QuestionKind {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Likert),
+            2 => Some(Self::YesNo),
+            3 => Some(Self::Choice),
+            4 => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Question {
+    pub id: u64,
+    pub kind: QuestionKind,
+    pub prompt: String,
+    pub per_dish: bool,
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u8>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+}
+
+impl Question {
+    fn new(dao: SurveyQuestionDAO) -> Result<Self> {
+        let kind = QuestionKind::from_code(dao.kind).ok_or_else(|| {
+            Error::InvalidParam(format!(
+                "Question {} has an unknown kind {}",
+                dao.id, dao.kind
+            ))
+        })?;
+        let choices = match dao.choices.as_deref() {
+            Some(choices) => serde_json::from_str(choices).map_err(|e| {
+                Error::InvalidParam(format!("Choices of question {}: {}", dao.id, e))
+            })?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            id: dao.id,
+            kind,
+            prompt: dao.prompt,
+            per_dish: dao.per_dish,
+            required: dao.required,
+            scale: dao.scale,
+            choices,
+        })
+    }
+
+    /// Checks an answer against the question and converts it to its stored values
+    fn store(&self, value: &Value) -> Result<(Option<u8>, Option<String>)> {
+        let invalid = |expected: &str| {
+            Err(Error::InvalidParam(format!(
+                "Question {} takes {}",
+                self.id, expected
+            )))
+        };
+
+        match self.kind {
+            QuestionKind::Likert => {
+                let scale = self.scale.unwrap_or(DEFAULT_SCALE);
+                match value.as_u64() {
+                    Some(point) if (1..=scale as u64).contains(&point) => {
+                        Ok((Some(point as u8), None))
+                    }
+                    _ => invalid(&format!("a number from 1 to {}", scale)),
+                }
+            }
+            QuestionKind::YesNo => match value.as_bool() {
+                Some(yes) => Ok((Some(yes as u8), None)),
+                None => invalid("true or false"),
+            },
+            QuestionKind::Choice => {
+                match value
+                    .as_str()
+                    .and_then(|choice| self.choices.iter().position(|c| c == choice))
+                {
+                    Some(index) => Ok((Some(index as u8), None)),
+                    None => invalid(&format!("one of {}", self.choices.join(", "))),
+                }
+            }
+            QuestionKind::Text => match value.as_str().map(str::trim) {
+                Some(text) if !text.is_empty() && text.chars().count() <= MAX_TEXT_ANSWER_CHARS => {
+                    Ok((None, Some(text.to_owned())))
+                }
+                _ => invalid(&format!(
+                    "text of 1 to {} characters",
+                    MAX_TEXT_ANSWER_CHARS
+                )),
+            },
+        }
+    }
+
+    /// Answer as the client sent it, from its stored values
+    fn load(&self, number_value: Option<u8>, text_value: Option<String>) -> Value {
+        match self.kind {
+            QuestionKind::Likert => number_value.map_or(Value::Null, Value::from),
+            QuestionKind::YesNo => number_value.map_or(Value::Null, |yes| Value::Bool(yes != 0)),
+            QuestionKind::Choice => number_value
+                .and_then(|index| self.choices.get(index as usize))
+                .map_or(Value::Null, |choice| Value::String(choice.clone())),
+            QuestionKind::Text => text_value.map_or(Value::Null, Value::String),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Template {
+    pub id: u64,
+    pub name: String,
+    pub version: u32,
+    /// None when asked for every meal
+    pub slot: Option<MealSlot>,
+    pub active_from: NaiveDate,
+    pub questions: Vec<Question>,
+}
+
+impl Template {
+    fn question(&self, id: u64) -> Option<&Question> {
+        self.questions.iter().find(|question| question.id == id)
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct QuestionParam {
+    pub kind: QuestionKind,
+    pub prompt: String,
+    #[serde(default)]
+    pub per_dish: bool,
+    #[serde(default)]
+    pub required: bool,
+    /// Highest point of a Likert question, 5 by default
+    pub scale: Option<u8>,
+    /// Options of a multiple choice question
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+impl QuestionParam {
+    fn into_new(self, n: usize) -> Result<NewQuestion> {
+        let invalid =
+            |problem: &str| Err(Error::InvalidParam(format!("Question {}: {}", n, problem)));
+
+        let prompt = self.prompt.trim().to_owned();
+        if prompt.is_empty() || prompt.chars().count() > 255 {
+            return invalid("`prompt` must be 1 to 255 characters");
+        }
+
+        let scale = match (self.kind, self.scale) {
+            (QuestionKind::Likert, scale) => {
+                let scale = scale.unwrap_or(DEFAULT_SCALE);
+                if !(2..=MAX_SCALE).contains(&scale) {
+                    return invalid(&format!("`scale` must be from 2 to {}", MAX_SCALE));
+                }
+                Some(scale)
+            }
+            (_, Some(_)) => return invalid("only Likert questions have a `scale`"),
+            (_, None) => None,
+        };
+
+        let choices = match self.kind {
+            QuestionKind::Choice => {
+                let choices = self
+                    .choices
+                    .iter()
+                    .map(|choice| choice.trim().to_owned())
+                    .collect::<Vec<_>>();
+                if !(2..=MAX_CHOICES).contains(&choices.len()) {
+                    return invalid(&format!("give 2 to {} `choices`", MAX_CHOICES));
+                }
+                if choices.iter().any(String::is_empty) {
+                    return invalid("`choices` must not be empty");
+                }
+                if (1..choices.len()).any(|i| choices[..i].contains(&choices[i])) {
+                    return invalid("`choices` must differ");
+                }
+                Some(
+                    serde_json::to_string(&choices)
+                        .map_err(|e| Error::InvalidParam(e.to_string()))?,
+                )
+            }
+            _ if !self.choices.is_empty() => {
+                return invalid("only multiple choice questions have `choices`")
+            }
+            _ => None,
+        };
+
+        Ok(NewQuestion {
+            kind: self.kind.code(),
+            prompt,
+            per_dish: self.per_dish,
+            required: self.required,
+            scale,
+            choices,
+        })
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TemplateParam {
+    /// Creating a template with an existing name makes its next version
+    pub name: String,
+    /// Leave it out to ask it for every meal
+    pub slot: Option<MealSlot>,
+    pub active_from: NaiveDate,
+    pub questions: Vec<QuestionParam>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AnswerReq {
+    pub question_id: u64,
+    /// Dish of a per-dish question
+    pub meal_item_id: Option<u64>,
+    /// A number for Likert, a boolean for yes/no, a string for multiple choice and text
+    pub value: Value,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Answer {
+    pub username: String,
+    pub question_id: u64,
+    pub meal_item_id: Option<u64>,
+    pub food_name: Option<String>,
+    pub date: NaiveDate,
+    pub slot: MealSlot,
+    pub value: Value,
+}
+
+/// Answers of a day to one template version, with the questions they answer
+#[derive(Clone, Serialize)]
+pub struct SurveyResult {
+    pub template: Template,
+    pub answers: Vec<Answer>,
+}
+
+/// Validated answers to the template active for a meal
+pub struct Submission {
+    pub meal_date: NaiveDate,
+    pub slot: MealSlot,
+    /// Every question of the template, so answers dropped from a resubmission are removed
+    pub question_ids: Vec<u64>,
+    pub answers: Vec<NewAnswer>,
+}
+
+/// Attaches the questions to each template, keeping the order of `templates`
+async fn with_questions(
+    pool: Data<DbPool>,
+    templates: Vec<SurveyTemplateDAO>,
+) -> Result<Vec<Template>> {
+    let ids = templates.iter().map(|template| template.id).collect();
+    let mut questions: HashMap<u64, Vec<Question>> = HashMap::new();
+    for question in SurveyQuestionDAO::by_templates(pool, ids).await? {
+        questions
+            .entry(question.template_id)
+            .or_default()
+            .push(Question::new(question)?);
+    }
+
+    Ok(templates
+        .into_iter()
+        .map(|template| Template {
+            id: template.id,
+            slot: template.meal_slot(),
+            questions: questions.remove(&template.id).unwrap_or_default(),
+            name: template.name,
+            version: template.version,
+            active_from: template.active_from,
+        })
+        .collect())
+}
+
+/// Template asked for a meal, if any
+pub async fn active(
+    pool: Data<DbPool>,
+    date: NaiveDate,
+    slot: MealSlot,
+) -> Result<Option<Template>> {
+    match SurveyTemplateDAO::active(pool.clone(), date, slot).await? {
+        Some(template) => Ok(with_questions(pool, vec![template]).await?.pop()),
+        None => Ok(None),
+    }
+}
+
+/// Every version of every template, latest version first
+pub async fn templates(pool: Data<DbPool>) -> Result<Vec<Template>> {
+    let templates = SurveyTemplateDAO::all(pool.clone()).await?;
+    with_questions(pool, templates).await
+}
+
+/// Saves a template as the next version of its name
+pub async fn create(pool: Data<DbPool>, param: TemplateParam, created_by: u64) -> Result<Template> {
+    let name = param.name.trim().to_owned();
+    if name.is_empty() || name.chars().count() > 255 {
+        return Err(Error::InvalidParam(String::from(
+            "`name` must be 1 to 255 characters",
+        )));
+    }
+    if param.questions.is_empty() {
+        return Err(Error::InvalidParam(String::from(
+            "A template needs at least one question",
+        )));
+    }
+    let questions = param
+        .questions
+        .into_iter()
+        .enumerate()
+        .map(|(i, question)| question.into_new(i + 1))
+        .collect::<Result<Vec<_>>>()?;
+
+    let template = SurveyTemplateDAO::create(
+        pool.clone(),
+        name,
+        param.slot,
+        param.active_from,
+        created_by,
+        questions,
+    )
+    .await?;
+    Ok(with_questions(pool, vec![template]).await?.remove(0))
+}
+
+/// Checks answers against the template active for a meal
+/// * Per-dish questions are answered once per dish of the meal, the others once
+/// * Every required question must be answered, for every dish if per-dish
+/// * Text answers go through the comment filter like rating comments
+pub async fn submission(
+    pool: Data<DbPool>,
+    filter: &CommentFilter,
+    meal_date: NaiveDate,
+    slot: MealSlot,
+    answers: Vec<AnswerReq>,
+) -> Result<Submission> {
+    let template = active(pool.clone(), meal_date, slot)
+        .await?
+        .ok_or_else(|| Error::InvalidParam(String::from("No survey is active for this meal")))?;
+    let items = MealItemDAO::by_meal(pool, meal_date, slot).await?;
+
+    let mut answered: Vec<(u64, Option<u64>)> = Vec::new();
+    let mut new_answers = Vec::new();
+    for answer in answers {
+        let question = template.question(answer.question_id).ok_or_else(|| {
+            Error::InvalidParam(format!(
+                "Question {} is not in survey {} version {}",
+                answer.question_id, template.name, template.version
+            ))
+        })?;
+
+        let food_name = match (question.per_dish, answer.meal_item_id) {
+            (true, Some(meal_item_id)) => match items.iter().find(|item| item.id == meal_item_id) {
+                Some(item) => Some(item.name.clone()),
+                None => {
+                    return Err(Error::InvalidParam(format!(
+                        "Dish {} is not on the menu of this meal",
+                        meal_item_id
+                    )))
+                }
+            },
+            (true, None) => {
+                return Err(Error::InvalidParam(format!(
+                    "Question {} is asked per dish; give `meal_item_id`",
+                    question.id
+                )))
+            }
+            (false, Some(_)) => {
+                return Err(Error::InvalidParam(format!(
+                    "Question {} is about the whole meal; leave out `meal_item_id`",
+                    question.id
+                )))
+            }
+            (false, None) => None,
+        };
+
+        let key = (question.id, answer.meal_item_id);
+        if answered.contains(&key) {
+            return Err(Error::InvalidParam(format!(
+                "Question {} is answered more than once",
+                question.id
+            )));
+        }
+        answered.push(key);
+
+        let (number_value, text_value) = question.store(&answer.value)?;
+        let text_value = filter.check(text_value)?;
+        new_answers.push(NewAnswer {
+            question_id: question.id,
+            meal_item_id: answer.meal_item_id,
+            food_name,
+            number_value,
+            text_value,
+        });
+    }
+
+    for question in template
+        .questions
+        .iter()
+        .filter(|question| question.required)
+    {
+        let missing = if question.per_dish {
+            items
+                .iter()
+                .any(|item| !answered.contains(&(question.id, Some(item.id))))
+        } else {
+            !answered.contains(&(question.id, None))
+        };
+        if missing {
+            return Err(Error::InvalidParam(format!(
+                "Question {} is required",
+                question.id
+            )));
+        }
+    }
+
+    Ok(Submission {
+        meal_date,
+        slot,
+        question_ids: template
+            .questions
+            .iter()
+            .map(|question| question.id)
+            .collect(),
+        answers: new_answers,
+    })
+}
+
+/// Answers of a day, grouped by the template version they answer
+pub async fn results(
+    pool: Data<DbPool>,
+    date: NaiveDate,
+    slot: Option<MealSlot>,
+) -> Result<Vec<SurveyResult>> {
+    let answers = SurveyAnswerDAO::with_users(pool.clone(), date, slot).await?;
+    if answers.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let question_ids = answers
+        .iter()
+        .map(|(answer, _)| answer.question_id)
+        .collect::<Vec<_>>();
+    let mut template_ids = SurveyQuestionDAO::by_ids(pool.clone(), question_ids)
+        .await?
+        .into_iter()
+        .map(|question| question.template_id)
+        .collect::<Vec<_>>();
+    template_ids.sort_unstable();
+    template_ids.dedup();
+    let templates = SurveyTemplateDAO::by_ids(pool.clone(), template_ids).await?;
+
+    let mut results = with_questions(pool, templates)
+        .await?
+        .into_iter()
+        .map(|template| SurveyResult {
+            template,
+            answers: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for (answer, user) in answers {
+        let found = results.iter_mut().find_map(|result| {
+            let question = result.template.question(answer.question_id)?;
+            let value = question.load(answer.number_value, answer.text_value.clone());
+            Some((result, value))
+        });
+        let (result, value) = match found {
+            Some(found) => found,
+            None => continue,
+        };
+        let slot = MealSlot::from_code(answer.slot).unwrap_or_default();
+        result.answers.push(Answer {
+            username: user.username,
+            question_id: answer.question_id,
+            meal_item_id: answer.meal_item_id,
+            food_name: answer.food_name,
+            date: answer.meal_date,
+            slot,
+            value,
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn question(kind: QuestionKind) -> Question {
+        Question {
+            id: 1,
+            kind,
+            prompt: String::from("맛있었나요?"),
+            per_dish: false,
+            required: true,
+            scale: None,
+            choices: vec![String::from("짜다"), String::from("싱겁다")],
+        }
+    }
+
+    #[test]
+    fn likert_takes_a_point_on_the_scale() {
+        let mut likert = question(QuestionKind::Likert);
+        assert_eq!(likert.store(&json!(1)).unwrap(), (Some(1), None));
+        assert_eq!(likert.store(&json!(5)).unwrap(), (Some(5), None));
+        assert!(likert.store(&json!(0)).is_err());
+        assert!(likert.store(&json!(6)).is_err());
+        assert!(likert.store(&json!("5")).is_err());
+
+        likert.scale = Some(10);
+        assert_eq!(likert.store(&json!(10)).unwrap(), (Some(10), None));
+    }
+
+    #[test]
+    fn yes_no_takes_a_boolean() {
+        let yes_no = question(QuestionKind::YesNo);
+        assert_eq!(yes_no.store(&json!(true)).unwrap(), (Some(1), None));
+        assert_eq!(yes_no.store(&json!(false)).unwrap(), (Some(0), None));
+        assert!(yes_no.store(&json!(1)).is_err());
+    }
+
+    #[test]
+    fn choice_is_stored_by_index() {
+        let choice = question(QuestionKind::Choice);
+        assert_eq!(choice.store(&json!("싱겁다")).unwrap(), (Some(1), None));
+        assert!(choice.store(&json!("달다")).is_err());
+        assert!(choice.store(&json!(0)).is_err());
+    }
+
+    #[test]
+    fn text_is_trimmed_and_limited() {
+        let text = question(QuestionKind::Text);
+        assert_eq!(
+            text.store(&json!("  너무 짜요 ")).unwrap(),
+            (None, Some(String::from("너무 짜요")))
+        );
+        assert!(text.store(&json!("   ")).is_err());
+        assert!(text
+            .store(&json!("가".repeat(MAX_TEXT_ANSWER_CHARS)))
+            .is_ok());
+        assert!(text
+            .store(&json!("가".repeat(MAX_TEXT_ANSWER_CHARS + 1)))
+            .is_err());
+    }
+
+    #[test]
+    fn load_gives_back_what_was_stored() {
+        for (kind, value) in [
+            (QuestionKind::Likert, json!(3)),
+            (QuestionKind::YesNo, json!(true)),
+            (QuestionKind::Choice, json!("짜다")),
+            (QuestionKind::Text, json!("좋아요")),
+        ] {
+            let question = question(kind);
+            let (number_value, text_value) = question.store(&value).unwrap();
+            assert_eq!(question.load(number_value, text_value), value);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::{Arc, RwLock},
+};
+
+use actix_web::{
+    http::{
+        header::{HOST, LOCATION},
+        uri::Authority,
+    },
+    HttpRequest, HttpResponse,
+};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{any_supported_type, CertifiedKey},
+    Certificate, PrivateKey, ServerConfig,
+};
+use rustls_pemfile::{read_all, Item};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::error::{Error, Result};
+
+// generating certs for localhost :
+//      openssl req -x509 -newkey rsa:4096 -nodes -keyout key.pem -out cert.pem -days 365 -subj '/CN=localhost'
+
+/// Reads every certificate in a PEM file, leaf first
+fn load_cert_chain(cert_location: &str) -> Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(File::open(cert_location)?);
+
+    let chain = read_all(&mut reader)?
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(der) => Some(Certificate(der)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if chain.is_empty() {
+        Err(Error::CertificateError(format!(
+            "No certificate found in {}",
+            cert_location
+        )))
+    } else {
+        Ok(chain)
+    }
+}
+
+/// Reads the first private key in a PEM file (PKCS#8, PKCS#1 RSA or SEC1 EC)
+fn load_private_key(key_location: &str) -> Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(key_location)?);
+
+    read_all(&mut reader)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| Error::CertificateError(format!("No private key found in {}", key_location)))
+}
+
+fn load_certified_key(cert_location: &str, key_location: &str) -> Result<CertifiedKey> {
+    let chain = load_cert_chain(cert_location)?;
+    let key = load_private_key(key_location)?;
+
+    let signing_key = any_supported_type(&key).map_err(|_| {
+        Error::CertificateError(format!("Unsupported private key type in {}", key_location))
+    })?;
+
+    Ok(CertifiedKey::new(chain, signing_key))
+}
+
+/// Serves the certificate currently loaded from cert_location/key_location
+/// * `reload` swaps it in place, so renewed certificates apply to new handshakes without a restart
+pub struct CertResolver {
+    cert_location: String,
+    key_location: String,
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertResolver {
+    pub fn new<T>(cert_location: T, key_location: T) -> Result<Self>
+    where
+        T: Into<String>,
+    {
+        let cert_location: String = cert_location.into();
+        let key_location: String = key_location.into();
+        let key = load_certified_key(&cert_location, &key_location)?;
+
+        Ok(Self {
+            cert_location,
+            key_location,
+            key: RwLock::new(Arc::new(key)),
+        })
+    }
+
+    /// Re-reads the files; the old certificate stays in use if they are broken
+    pub fn reload(&self) -> Result<()> {
+        let key = load_certified_key(&self.cert_location, &self.key_location)?;
+        *self.key.write().unwrap() = Arc::new(key);
+
+        Ok(())
+    }
+
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.read().unwrap().clone())
+    }
+}
+
+/// Reloads the certificate every time the process receives SIGHUP
+pub fn reload_on_sighup(resolver: Arc<CertResolver>) -> Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    actix_web::rt::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match resolver.reload() {
+                Ok(()) => log::info!("Reloaded certificate"),
+                Err(e) => log::error!("Reloading certificate failed, keeping the old one ({})", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Handler of the plain HTTP listener; sends everything to the HTTPS port
+pub fn redirect_to_https(req: HttpRequest, https_port: u16) -> HttpResponse {
+    // Authority keeps the brackets of an IPv6 host like [::1] and drops only the port
+    let authority = req
+        .headers()
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok());
+    let host = authority
+        .as_ref()
+        .map(Authority::host)
+        .unwrap_or("localhost");
+
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    let location = if https_port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    };
+
+    HttpResponse::PermanentRedirect()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn location(host: Option<&str>, uri: &str, https_port: u16) -> String {
+        let mut req = TestRequest::get().uri(uri);
+        if let Some(host) = host {
+            req = req.insert_header((HOST, host));
+        }
+        let response = redirect_to_https(req.to_http_request(), https_port);
+
+        assert_eq!(response.status(), 308);
+        response
+            .headers()
+            .get(LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[test]
+    fn redirects_to_the_https_port() {
+        assert_eq!(
+            location(Some("meal.school.kr:8080"), "/meal?date=2026-10-18", 8443),
+            "https://meal.school.kr:8443/meal?date=2026-10-18"
+        );
+        assert_eq!(
+            location(Some("meal.school.kr"), "/", 443),
+            "https://meal.school.kr/"
+        );
+    }
+
+    #[test]
+    fn keeps_ipv6_hosts_whole() {
+        assert_eq!(
+            location(Some("[::1]:8080"), "/", 8443),
+            "https://[::1]:8443/"
+        );
+        assert_eq!(
+            location(Some("[2001:db8::1]"), "/login", 443),
+            "https://[2001:db8::1]/login"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_localhost() {
+        assert_eq!(location(None, "/", 8443), "https://localhost:8443/");
+        assert_eq!(location(Some("bad host"), "/", 443), "https://localhost/");
+    }
+}
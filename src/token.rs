@@ -1,10 +1,30 @@
-// use super::{db::models::User, error::Result};
-// use chrono::Utc;
-// use jsonwebtoken::{EncodingKey, Header};
+use crate::{
+    db::models::{
+        session::{SessionConfig, SessionDAO},
+        user::UserDAO,
+    },
+    error::{Error, Result},
+};
+use chrono::Utc;
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-// pub static KEY: [u8; 16] = *include_bytes!("../secret.key");
+/// Keys derived from `token_generation_key` in config.yml
+/// * Shared with handlers through `Data<TokenKey>`
+pub struct TokenKey {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl TokenKey {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct UserToken {
@@ -14,31 +34,115 @@ pub struct UserToken {
     pub expire_at: i64,
 }
 
-// const ONE_MONTH: i64 = 60 * 60 * 24 * 30;
 impl UserToken {
     pub fn new_session_id() -> String {
         Uuid::new_v4().as_simple().to_string()
     }
 
-    //TODO: used in future
-    /*pub fn generate(user: &User) -> Result<String> {
-        if let Some(session_id) = user.session_id.clone() {
-            let now = Utc::now().timestamp_nanos() / 1_000_000_000;
-            let payload = UserToken {
-                user: user.user_id.clone(),
-                session: session_id,
-                issued_at: now,
-                expire_at: now + ONE_MONTH,
-            };
-
-            jsonwebtoken::encode(
-                &Header::default(),
-                &payload,
-                &EncodingKey::from_secret(&KEY),
-            )
+    /// Signs a token for a freshly created session
+    /// * Lives as long as the session's absolute timeout; idle expiry is tracked in the DB
+    pub fn generate(
+        user: &UserDAO,
+        session: &SessionDAO,
+        config: &SessionConfig,
+        key: &TokenKey,
+    ) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let payload = UserToken {
+            user: user.username.clone(),
+            session: session.id.clone(),
+            issued_at: now,
+            expire_at: now + config.absolute_timeout_secs,
+        };
+
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &payload, &key.encoding)
             .map_err(Into::into)
+    }
+
+    /// Verifies signature and expiry of a token
+    /// * Whether the session is still alive is checked against the sessions table
+    pub fn decode(token: &str, key: &TokenKey) -> Result<Self> {
+        // Expiry lives in `expire_at` instead of the registered `exp` claim
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+
+        let token = jsonwebtoken::decode::<UserToken>(token, &key.decoding, &validation)?.claims;
+
+        if token.expire_at <= Utc::now().timestamp() {
+            Err(Error::TokenExpired)
         } else {
-            unimplemented!()
+            Ok(token)
         }
-    }*/
+    }
+}
+
+/// `check_in` section of config.yml
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckInConfig {
+    /// How long a check-in code stays valid; clients fetch a new one before then
+    pub code_ttl_secs: i64,
+}
+
+impl Default for CheckInConfig {
+    fn default() -> Self {
+        Self { code_ttl_secs: 60 }
+    }
+}
+
+/// Audience of check-in codes, so session tokens can't be scanned and codes can't log in
+const CHECK_IN_AUDIENCE: &str = "check-in";
+
+/// Payload of the QR code a student shows at the dining hall
+#[derive(Serialize, Deserialize)]
+pub struct CheckInToken {
+    pub aud: String,
+    pub user: String,
+    /// Makes every code unique, so a code rotates even within the same second
+    pub nonce: String,
+    pub issued_at: i64,
+    pub expire_at: i64,
+}
+
+impl CheckInToken {
+    pub fn generate(
+        user: &UserDAO,
+        config: &CheckInConfig,
+        key: &TokenKey,
+    ) -> Result<(String, i64)> {
+        let now = Utc::now().timestamp();
+        let payload = CheckInToken {
+            aud: String::from(CHECK_IN_AUDIENCE),
+            user: user.username.clone(),
+            nonce: Uuid::new_v4().as_simple().to_string(),
+            issued_at: now,
+            expire_at: now + config.code_ttl_secs,
+        };
+
+        let code = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &payload, &key.encoding)?;
+        Ok((code, payload.expire_at))
+    }
+
+    pub fn decode(code: &str, key: &TokenKey) -> Result<Self> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        validation.set_audience(&[CHECK_IN_AUDIENCE]);
+
+        let token = jsonwebtoken::decode::<CheckInToken>(code.trim(), &key.decoding, &validation)
+            .map_err(|e| {
+                Error::InvalidCheckInCode(match e.kind() {
+                    ErrorKind::InvalidAudience => String::from("not a check-in code"),
+                    _ => e.to_string(),
+                })
+            })?
+            .claims;
+
+        if token.expire_at <= Utc::now().timestamp() {
+            Err(Error::InvalidCheckInCode(String::from("expired")))
+        } else {
+            Ok(token)
+        }
+    }
 }